@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::error::{AppError, AppResult};
+use crate::jobs::{self, JobRegistry, JobStatus};
+use crate::naming;
+use crate::{ConversionOptions, ConversionResult};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchConversionOptions {
+    pub template: ConversionOptions, // Shared settings, output_path is ignored
+    #[serde(default)]
+    pub output_dir: Option<String>, // Overrides template.output_dir
+    #[serde(default)]
+    pub filename_pattern: Option<String>, // Overrides template.filename_template, {index} is the position in the batch
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+}
+
+fn default_max_concurrency() -> usize {
+    2
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchItemResult {
+    pub file_path: String,
+    pub status: JobStatus,
+    pub result: Option<ConversionResult>,
+    pub error: Option<AppError>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+}
+
+impl BatchSummary {
+    pub fn tally(statuses: impl IntoIterator<Item = JobStatus>) -> Self {
+        let mut summary = Self::default();
+        for status in statuses {
+            summary.total += 1;
+            match status {
+                JobStatus::Completed => summary.succeeded += 1,
+                JobStatus::Skipped => summary.skipped += 1,
+                JobStatus::Failed => summary.failed += 1,
+                JobStatus::Cancelled => summary.cancelled += 1,
+            }
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchConversionResult {
+    pub job_id: String,
+    pub items: Vec<BatchItemResult>,
+    pub summary: BatchSummary,
+}
+
+// One item of a bounded queue. `job_id` is the item's own "<batch id>/<n>" job id.
+pub(crate) struct QueueItem<F> {
+    pub job_id: String,
+    pub label: String, // Shown as current_file in the batch progress events
+    pub work: F,
+}
+
+// Run the items at most `max_concurrency` at a time under the batch job `job_id`, so cancelling the
+// batch stops queued items from starting. Outcomes come back in the order of `items`.
+pub(crate) async fn run_queue<F, T>(
+    app: &AppHandle,
+    job_id: &str,
+    max_concurrency: usize,
+    items: Vec<QueueItem<F>>,
+) -> AppResult<Vec<AppResult<T>>>
+where
+    F: Future<Output = AppResult<T>> + Send + 'static,
+    T: Send + 'static,
+{
+    let batch = app.state::<JobRegistry>().register(job_id)?;
+    let semaphore = Arc::new(Semaphore::new(max_concurrency.max(1)));
+    let total = items.len();
+    let mut labels = Vec::with_capacity(total);
+    let mut tasks = JoinSet::new();
+
+    for (position, item) in items.into_iter().enumerate() {
+        let semaphore = semaphore.clone();
+        let batch_token = batch.token.clone();
+        labels.push(item.label);
+
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+
+            let outcome = if batch_token.is_cancelled() {
+                Err(AppError::Cancelled { job_id: item.job_id })
+            } else {
+                item.work.await
+            };
+
+            (position, outcome)
+        });
+    }
+
+    let mut outcomes: Vec<Option<AppResult<T>>> = labels.iter().map(|_| None).collect();
+    let mut finished = 0;
+
+    while let Some(joined) = tasks.join_next().await {
+        let Ok((position, outcome)) = joined else {
+            continue;
+        };
+
+        finished += 1;
+        let percentage = finished as f32 / total as f32 * 100.0;
+        jobs::emit_progress(app, job_id, percentage, "processing", &labels[position]);
+        outcomes[position] = Some(outcome);
+    }
+
+    let status = if batch.token.is_cancelled() { "cancelled" } else { "completed" };
+    jobs::emit_progress(app, job_id, 100.0, status, "");
+
+    Ok(outcomes
+        .into_iter()
+        .map(|outcome| {
+            outcome.unwrap_or_else(|| {
+                Err(AppError::Io {
+                    message: "Batch worker stopped unexpectedly".to_string(),
+                })
+            })
+        })
+        .collect())
+}
+
+pub async fn run_batch(
+    app: AppHandle,
+    job_id: String,
+    file_paths: Vec<String>,
+    options: BatchConversionOptions,
+) -> AppResult<BatchConversionResult> {
+    if file_paths.is_empty() {
+        return Err(AppError::invalid_option("No input files given"));
+    }
+
+    // Items share the single-file naming settings unless the batch overrides them
+    let output_dir = options
+        .output_dir
+        .or_else(|| options.template.output_dir.clone())
+        .ok_or_else(|| AppError::invalid_option("Batch conversions need an output_dir"))?;
+    let filename_pattern = options
+        .filename_pattern
+        .or_else(|| options.template.filename_template.clone())
+        .unwrap_or_else(|| naming::DEFAULT_TEMPLATE.to_string());
+
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| AppError::io("Failed to create output directory", e))?;
+
+    let queue = file_paths
+        .iter()
+        .enumerate()
+        .map(|(index, file_path)| {
+            let mut item_options = options.template.clone();
+            item_options.output_dir = Some(output_dir.clone());
+            item_options.filename_template = Some(filename_pattern.replace("{index}", &(index + 1).to_string()));
+
+            let app = app.clone();
+            let item_job_id = format!("{}/{}", job_id, index + 1);
+            let file_path = file_path.clone();
+
+            QueueItem {
+                job_id: item_job_id.clone(),
+                label: file_path.clone(),
+                work: async move { crate::convert_file(&app, item_job_id, file_path, item_options).await },
+            }
+        })
+        .collect();
+
+    let outcomes = run_queue(&app, &job_id, options.max_concurrency.clamp(1, 16), queue).await?;
+
+    let items: Vec<BatchItemResult> = file_paths
+        .into_iter()
+        .zip(outcomes)
+        .map(|(file_path, outcome)| match outcome {
+            Ok(result) => BatchItemResult {
+                file_path,
+                status: if result.skipped { JobStatus::Skipped } else { JobStatus::Completed },
+                result: Some(result),
+                error: None,
+            },
+            Err(e) => BatchItemResult {
+                file_path,
+                status: if e.is_cancelled() { JobStatus::Cancelled } else { JobStatus::Failed },
+                result: None,
+                error: Some(e),
+            },
+        })
+        .collect();
+
+    let summary = BatchSummary::tally(items.iter().map(|item| item.status));
+
+    Ok(BatchConversionResult {
+        job_id,
+        items,
+        summary,
+    })
+}
@@ -0,0 +1,505 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tauri::{AppHandle, Emitter, Manager, Url};
+use tokio::process::Command;
+
+use crate::error::{AppError, AppResult};
+use crate::jobs::{self, JobRegistry, ProcessOutcome};
+use crate::tools::{Tool, ToolLocator};
+use crate::AudioFile;
+
+pub const DOWNLOAD_PROGRESS_EVENT: &str = "download-progress";
+
+// Progress lines are JSON objects behind a marker. Every value is a quoted string because
+// yt-dlp fills in "NA" for fields it doesn't know yet, which isn't a JSON number.
+const DOWNLOAD_MARKER: &str = "[akc-download] ";
+const POSTPROCESS_MARKER: &str = "[akc-postprocess] ";
+const DOWNLOAD_TEMPLATE: &str = concat!(
+    "download:[akc-download] ",
+    r#"{"status": "%(progress.status)s", "downloaded": "%(progress.downloaded_bytes)s", "#,
+    r#""total": "%(progress.total_bytes)s", "estimate": "%(progress.total_bytes_estimate)s", "#,
+    r#""speed": "%(progress.speed)s", "eta": "%(progress.eta)s"}"#,
+);
+const POSTPROCESS_TEMPLATE: &str = concat!(
+    "postprocess:[akc-postprocess] ",
+    r#"{"status": "%(progress.status)s", "postprocessor": "%(progress.postprocessor)s"}"#,
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadPhase {
+    Downloading,
+    Extracting, // Converting the downloaded stream to the requested audio format
+    PostProcessing, // Tagging, moving files into place
+}
+
+// Payload of "download-progress" events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadProgress {
+    pub job_id: String,
+    pub phase: DownloadPhase,
+    pub downloaded_bytes: Option<u64>,
+    pub total_bytes: Option<u64>, // Exact size, or yt-dlp's estimate for fragmented streams
+    pub speed: Option<f64>, // Bytes per second
+    pub eta: Option<f64>, // Seconds
+    pub percentage: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct DownloadLine {
+    status: String,
+    downloaded: String,
+    total: String,
+    estimate: String,
+    speed: String,
+    eta: String,
+}
+
+#[derive(Deserialize)]
+struct PostprocessLine {
+    status: String,
+    postprocessor: String,
+}
+
+fn number(value: &str) -> Option<f64> {
+    value.trim().parse::<f64>().ok().filter(|value| value.is_finite())
+}
+
+// Turn one line of yt-dlp output into a progress event, if it is a progress line
+fn parse_progress(job_id: &str, line: &str) -> Option<DownloadProgress> {
+    if let Some(json) = line.strip_prefix(DOWNLOAD_MARKER) {
+        let progress: DownloadLine = serde_json::from_str(json).ok()?;
+        let downloaded = number(&progress.downloaded).map(|bytes| bytes as u64);
+        let total = number(&progress.total)
+            .or_else(|| number(&progress.estimate))
+            .map(|bytes| bytes as u64);
+        let percentage = match (progress.status.as_str(), downloaded, total) {
+            ("finished", _, _) => Some(100.0),
+            (_, Some(downloaded), Some(total)) if total > 0 => Some((downloaded as f64 / total as f64 * 100.0).min(100.0) as f32),
+            _ => None,
+        };
+
+        return Some(DownloadProgress {
+            job_id: job_id.to_string(),
+            phase: DownloadPhase::Downloading,
+            downloaded_bytes: downloaded,
+            total_bytes: total,
+            speed: number(&progress.speed),
+            eta: number(&progress.eta),
+            percentage,
+        });
+    }
+
+    let json = line.strip_prefix(POSTPROCESS_MARKER)?;
+    let progress: PostprocessLine = serde_json::from_str(json).ok()?;
+    let phase = match progress.postprocessor.as_str() {
+        "ExtractAudio" => DownloadPhase::Extracting,
+        _ => DownloadPhase::PostProcessing,
+    };
+
+    Some(DownloadProgress {
+        job_id: job_id.to_string(),
+        phase,
+        downloaded_bytes: None,
+        total_bytes: None,
+        speed: None,
+        eta: None,
+        percentage: (progress.status == "finished").then_some(100.0),
+    })
+}
+
+// Audio format of downloaded files. The default keeps the stream exactly as the site serves it
+// (usually Opus or AAC), so later pitch shifting starts from the best available source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "codec", rename_all = "snake_case")]
+pub enum DownloadFormat {
+    #[default]
+    Original,
+    Flac,
+    Wav,
+    Mp3 {
+        #[serde(default)]
+        quality: u8, // LAME VBR quality, 0 (best) to 10
+    },
+}
+
+impl DownloadFormat {
+    // -x on its own extracts the audio track without re-encoding when the container allows it
+    fn args(self) -> AppResult<Vec<String>> {
+        let mut args = vec!["-x".to_string()];
+
+        match self {
+            DownloadFormat::Original => {}
+            DownloadFormat::Flac => args.extend(["--audio-format".into(), "flac".into()]),
+            DownloadFormat::Wav => args.extend(["--audio-format".into(), "wav".into()]),
+            DownloadFormat::Mp3 { quality } if quality > 10 => {
+                return Err(AppError::invalid_option(format!("MP3 quality must be between 0 and 10, got {}", quality)));
+            }
+            DownloadFormat::Mp3 { quality } => args.extend([
+                "--audio-format".into(), "mp3".into(),
+                "--audio-quality".into(), quality.to_string(),
+            ]),
+        }
+
+        Ok(args)
+    }
+}
+
+// What `lookup_media` reports about a URL before anything is downloaded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaInfo {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub uploader: Option<String>,
+    pub duration: Option<f64>,
+    pub thumbnail: Option<String>, // URL
+    pub webpage_url: Option<String>,
+    pub extractor: Option<String>,
+    pub audio_formats: Vec<MediaFormat>, // Best quality last, the order yt-dlp uses
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaFormat {
+    pub format_id: String, // Pass to download_media to fetch exactly this format
+    pub ext: Option<String>,
+    pub acodec: Option<String>,
+    pub abr: Option<f64>, // Audio bitrate in kbps
+    pub asr: Option<u32>, // Sample rate
+    pub audio_channels: Option<u32>,
+    pub filesize: Option<u64>, // Exact or approximate size in bytes
+    pub format_note: Option<String>,
+    pub audio_only: bool, // False for formats that also carry video, audio is extracted after download
+}
+
+// The subset of `yt-dlp -J` we use
+#[derive(Deserialize)]
+struct InfoJson {
+    id: Option<String>,
+    title: Option<String>,
+    uploader: Option<String>,
+    channel: Option<String>,
+    duration: Option<f64>,
+    thumbnail: Option<String>,
+    webpage_url: Option<String>,
+    extractor_key: Option<String>,
+    #[serde(default)]
+    formats: Vec<FormatJson>,
+}
+
+#[derive(Deserialize)]
+struct FormatJson {
+    format_id: String,
+    ext: Option<String>,
+    acodec: Option<String>,
+    vcodec: Option<String>,
+    abr: Option<f64>,
+    asr: Option<u32>,
+    audio_channels: Option<u32>,
+    filesize: Option<u64>,
+    filesize_approx: Option<u64>,
+    format_note: Option<String>,
+}
+
+// Which yt-dlp extractors downloads may use, persisted next to the tool settings.
+// None allows everything the bundled yt-dlp supports.
+#[derive(Default)]
+pub struct ExtractorAllowList {
+    config_path: Option<PathBuf>,
+    allowed: Mutex<Option<Vec<String>>>,
+}
+
+impl ExtractorAllowList {
+    pub fn new(config_path: Option<PathBuf>) -> Self {
+        let allowed = config_path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        Self {
+            config_path,
+            allowed: Mutex::new(allowed),
+        }
+    }
+
+    pub fn get(&self) -> Option<Vec<String>> {
+        self.allowed.lock().unwrap().clone()
+    }
+
+    // Names are checked against `known`, the output of `yt-dlp --list-extractors`, and stored in its spelling
+    pub fn set(&self, allowed: Option<Vec<String>>, known: &[String]) -> AppResult<Option<Vec<String>>> {
+        let allowed = match allowed {
+            Some(names) => {
+                let by_lowercase: BTreeMap<String, &String> = known.iter()
+                    .map(|name| (name.to_lowercase(), name))
+                    .collect();
+
+                let mut canonical = Vec::new();
+                for name in names {
+                    match by_lowercase.get(&name.trim().to_lowercase()) {
+                        Some(known_name) => canonical.push((*known_name).clone()),
+                        None => return Err(AppError::invalid_option(format!("yt-dlp has no extractor named {}", name))),
+                    }
+                }
+                canonical.sort();
+                canonical.dedup();
+                Some(canonical)
+            }
+            None => None,
+        };
+
+        if let Some(config_path) = &self.config_path {
+            if let Some(parent) = config_path.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| AppError::io("Failed to create config directory", e))?;
+            }
+            std::fs::write(config_path, serde_json::to_string_pretty(&allowed)?)
+                .map_err(|e| AppError::io("Failed to save download settings", e))?;
+        }
+
+        *self.allowed.lock().unwrap() = allowed.clone();
+        Ok(allowed)
+    }
+
+    // yt-dlp treats an unlisted site as an unsupported URL, so the allow-list is enforced by yt-dlp itself
+    pub fn args(&self) -> Vec<String> {
+        match self.allowed.lock().unwrap().as_ref() {
+            Some(names) => vec!["--use-extractors".to_string(), names.join(",")],
+            None => Vec::new(),
+        }
+    }
+}
+
+// Only plain web URLs are handed to yt-dlp, never local paths or other schemes
+pub fn parse_media_url(url: &str) -> AppResult<Url> {
+    let invalid = || AppError::InvalidUrl { url: url.to_string() };
+    let parsed = Url::parse(url.trim()).map_err(|_| invalid())?;
+
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none_or(str::is_empty) {
+        return Err(invalid());
+    }
+    Ok(parsed)
+}
+
+// Title, uploader, duration, thumbnail and audio formats of a single item, without downloading it
+pub async fn lookup_media(app: &AppHandle, url: &str) -> AppResult<MediaInfo> {
+    let parsed = parse_media_url(url)?;
+    let ytdlp_path = app.state::<ToolLocator>().resolve(Tool::YtDlp)?;
+
+    let output = Command::new(ytdlp_path)
+        .args(app.state::<ExtractorAllowList>().args())
+        .args(["-J", "--skip-download", "--no-playlist", parsed.as_str()])
+        .output()
+        .await
+        .map_err(|e| AppError::io("Failed to execute yt-dlp", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("Unsupported URL") {
+            return Err(AppError::InvalidUrl { url: url.to_string() });
+        }
+        return Err(AppError::tool_failed("yt-dlp", output.status, &stderr));
+    }
+
+    let info: InfoJson = serde_json::from_slice(&output.stdout)
+        .map_err(|e| AppError::parse("Failed to parse yt-dlp media info", e))?;
+
+    let has = |codec: &Option<String>| codec.as_deref().is_some_and(|codec| codec != "none");
+    let audio_formats = info.formats
+        .into_iter()
+        .filter(|format| has(&format.acodec))
+        .map(|format| MediaFormat {
+            audio_only: !has(&format.vcodec),
+            format_id: format.format_id,
+            ext: format.ext,
+            acodec: format.acodec,
+            abr: format.abr,
+            asr: format.asr,
+            audio_channels: format.audio_channels,
+            filesize: format.filesize.or(format.filesize_approx),
+            format_note: format.format_note,
+        })
+        .collect();
+
+    Ok(MediaInfo {
+        id: info.id,
+        title: info.title,
+        uploader: info.uploader.or(info.channel),
+        duration: info.duration,
+        thumbnail: info.thumbnail,
+        webpage_url: info.webpage_url,
+        extractor: info.extractor_key,
+        audio_formats,
+    })
+}
+
+// Extractor names from `yt-dlp --list-extractors`, e.g. "youtube", "soundcloud", "Bandcamp"
+pub async fn list_extractors(ytdlp_path: &Path) -> AppResult<Vec<String>> {
+    let output = Command::new(ytdlp_path)
+        .arg("--list-extractors")
+        .output()
+        .await
+        .map_err(|e| AppError::io("Failed to execute yt-dlp", e))?;
+
+    if !output.status.success() {
+        return Err(AppError::tool_failed("yt-dlp", output.status, &String::from_utf8_lossy(&output.stderr)));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let extractors = stdout
+        .lines()
+        // Broken extractors are listed as "name (CURRENTLY BROKEN)"
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect();
+
+    Ok(extractors)
+}
+
+// yt-dlp writes the stream to "<stem>.<ext>.part", keeps resume state in "<stem>.<ext>.ytdl" and
+// extraction writes "<stem>.<audio ext>" next to them. Only files written since `started` are
+// removed, so an older file that happens to share the title is left alone.
+fn remove_partial_output(download_path: &Path, started: SystemTime) {
+    let (Some(dir), Some(stem)) = (download_path.parent(), download_path.file_stem().and_then(|s| s.to_str())) else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    let prefix = format!("{}.", stem);
+    for entry in entries.flatten() {
+        let ours = entry.file_name().to_str().is_some_and(|name| name.starts_with(&prefix));
+        let fresh = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified >= started);
+
+        if ours && fresh {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+// yt-dlp's exit code when --break-match-filters stopped the download
+const PLAYLIST_REJECTED_EXIT_CODE: i32 = 101;
+
+const SINGLE_FILE_TEMPLATE: &str = "%(title)s.%(ext)s";
+
+// Download the audio track of any page yt-dlp can handle. Without a format id yt-dlp picks the
+// best audio, otherwise that exact format from `lookup_media` is fetched.
+pub async fn download_audio(
+    app: &AppHandle,
+    job_id: String,
+    url: String,
+    output_dir: String,
+    format_id: Option<String>,
+    audio_format: DownloadFormat,
+) -> AppResult<serde_json::Value> {
+    let file_info = download_file(app, &job_id, &url, &output_dir, SINGLE_FILE_TEMPLATE, format_id.as_deref(), audio_format).await?;
+
+    Ok(serde_json::json!({
+        "success": true,
+        "job_id": job_id,
+        "message": format!("Successfully downloaded: {}", url),
+        "file": file_info
+    }))
+}
+
+// Download a single item and read back the saved file. A URL that points at a video inside a
+// playlist downloads only the video, a URL that is only a playlist is rejected before anything is
+// downloaded, whole playlists go through `playlist::download_playlist`. `file_template` is a yt-dlp
+// output template for the file name inside `output_dir`.
+pub async fn download_file(
+    app: &AppHandle,
+    job_id: &str,
+    url: &str,
+    output_dir: &str,
+    file_template: &str,
+    format_id: Option<&str>,
+    audio_format: DownloadFormat,
+) -> AppResult<Option<AudioFile>> {
+    let parsed = parse_media_url(url)?;
+
+    // With -x and no -f, yt-dlp already selects "bestaudio/best"
+    let mut format_args: Vec<String> = match format_id.map(str::trim) {
+        Some("") => return Err(AppError::invalid_option("Format id must not be empty")),
+        Some(format_id) => vec!["-f".to_string(), format_id.to_string()],
+        None => Vec::new(),
+    };
+    format_args.extend(audio_format.args()?);
+
+    let tools = app.state::<ToolLocator>();
+    let ytdlp_path = tools.resolve(Tool::YtDlp)?;
+    let output_template = format!("{}/{}", output_dir, file_template);
+
+    let mut command = Command::new(ytdlp_path);
+    command
+        .args(app.state::<ExtractorAllowList>().args())
+        .args(format_args)
+        .args([
+            "--no-simulate",
+            "--no-playlist",
+            // Playlist entries carry a playlist_id, so yt-dlp stops at the first one with exit code 101
+            "--break-match-filters", "!playlist_id",
+            // --print implies --quiet, which would hide the progress lines
+            "--no-quiet",
+            "--newline",
+            "--progress-template", DOWNLOAD_TEMPLATE,
+            "--progress-template", POSTPROCESS_TEMPLATE,
+            "--print", "before_dl:[akc-file] download:%(filename)s",
+            "--print", "after_move:[akc-file] saved:%(filepath)s",
+            "-o", &output_template,
+            parsed.as_str(),
+        ]);
+
+    let job = app.state::<JobRegistry>().register(job_id)?;
+    let started = SystemTime::now();
+    let mut partial_file: Option<String> = None;
+    let mut final_file: Option<String> = None;
+
+    // Without --quiet, yt-dlp's regular log lines share stdout with the lines we asked for
+    let outcome = jobs::run_cancellable(command, "yt-dlp", &job.token, |line| {
+        let line = line.trim();
+        if let Some(progress) = parse_progress(job_id, line) {
+            let _ = app.emit(DOWNLOAD_PROGRESS_EVENT, progress);
+        } else if let Some(path) = line.strip_prefix("[akc-file] download:") {
+            partial_file = Some(path.to_string());
+        } else if let Some(path) = line.strip_prefix("[akc-file] saved:") {
+            final_file = Some(path.to_string());
+        }
+    }).await?;
+
+    match outcome {
+        ProcessOutcome::Cancelled => {
+            if let Some(path) = partial_file {
+                remove_partial_output(Path::new(&path), started);
+            }
+
+            return Err(AppError::Cancelled { job_id: job_id.to_string() });
+        }
+        ProcessOutcome::Exited { status, .. } if status.code() == Some(PLAYLIST_REJECTED_EXIT_CODE) => {
+            return Err(AppError::invalid_option(format!(
+                "{} is a playlist, download its entries with download_playlist instead",
+                url,
+            )));
+        }
+        // Also what a site outside the extractor allow-list looks like
+        ProcessOutcome::Exited { status, stderr } if !status.success() && stderr.contains("Unsupported URL") => {
+            return Err(AppError::InvalidUrl { url: url.to_string() });
+        }
+        ProcessOutcome::Exited { status, stderr } if !status.success() => {
+            return Err(AppError::tool_failed("yt-dlp", status, &stderr));
+        }
+        ProcessOutcome::Exited { .. } => {}
+    }
+
+    // The after_move line holds the final file path
+    match final_file {
+        Some(file_path) if Path::new(&file_path).exists() => Ok(crate::read_audio_info(&tools, file_path, false).await.ok()),
+        _ => Ok(None),
+    }
+}
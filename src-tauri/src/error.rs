@@ -0,0 +1,147 @@
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+use std::fmt;
+use std::process::ExitStatus;
+
+// Keep this many trailing stderr lines from a failed tool, the rest is usually banner noise
+const STDERR_TAIL_LINES: usize = 20;
+
+// Errors returned by every command. They serialize as `{ "code": "...", "message": "...", ... }`
+// so the frontend can branch on the stable code instead of matching message text.
+#[derive(Debug)]
+pub enum AppError {
+    MissingTool { tool: String },
+    InputNotFound { path: String },
+    OutputExists { path: String },
+    ToolFailed { tool: String, exit_code: Option<i32>, stderr_tail: String },
+    InvalidUrl { url: String },
+    InvalidOption { message: String },
+    Unsupported { message: String },
+    Parse { message: String },
+    Analysis { message: String },
+    Cancelled { job_id: String },
+    Io { message: String },
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::MissingTool { .. } => "missing_tool",
+            Self::InputNotFound { .. } => "input_not_found",
+            Self::OutputExists { .. } => "output_exists",
+            Self::ToolFailed { .. } => "tool_failed",
+            Self::InvalidUrl { .. } => "invalid_url",
+            Self::InvalidOption { .. } => "invalid_option",
+            Self::Unsupported { .. } => "unsupported",
+            Self::Parse { .. } => "parse",
+            Self::Analysis { .. } => "analysis",
+            Self::Cancelled { .. } => "cancelled",
+            Self::Io { .. } => "io",
+        }
+    }
+
+    pub fn tool_failed(tool: &str, status: ExitStatus, stderr: &str) -> Self {
+        let lines: Vec<&str> = stderr.trim_end().lines().collect();
+        let tail = lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].join("\n");
+
+        Self::ToolFailed {
+            tool: tool.to_string(),
+            exit_code: status.code(),
+            stderr_tail: tail,
+        }
+    }
+
+    pub fn io(context: &str, error: impl fmt::Display) -> Self {
+        Self::Io {
+            message: format!("{}: {}", context, error),
+        }
+    }
+
+    pub fn parse(context: &str, error: impl fmt::Display) -> Self {
+        Self::Parse {
+            message: format!("{}: {}", context, error),
+        }
+    }
+
+    pub fn invalid_option(message: impl Into<String>) -> Self {
+        Self::InvalidOption {
+            message: message.into(),
+        }
+    }
+
+    pub fn unsupported(message: impl Into<String>) -> Self {
+        Self::Unsupported {
+            message: message.into(),
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Self::Cancelled { .. })
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingTool { tool } => write!(f, "{} binary not found", tool),
+            Self::InputNotFound { path } => write!(f, "File does not exist: {}", path),
+            Self::OutputExists { path } => write!(f, "Output file already exists: {}", path),
+            Self::ToolFailed { tool, exit_code: Some(code), stderr_tail } => {
+                write!(f, "{} exited with code {}: {}", tool, code, stderr_tail)
+            }
+            Self::ToolFailed { tool, exit_code: None, stderr_tail } => {
+                write!(f, "{} was terminated: {}", tool, stderr_tail)
+            }
+            Self::InvalidUrl { url } => write!(f, "Invalid URL: {}", url),
+            Self::InvalidOption { message }
+            | Self::Unsupported { message }
+            | Self::Parse { message }
+            | Self::Analysis { message }
+            | Self::Io { message } => write!(f, "{}", message),
+            Self::Cancelled { job_id } => write!(f, "Job {} was cancelled", job_id),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("code", self.code())?;
+        map.serialize_entry("message", &self.to_string())?;
+
+        match self {
+            Self::MissingTool { tool } => map.serialize_entry("tool", tool)?,
+            Self::InputNotFound { path } | Self::OutputExists { path } => map.serialize_entry("path", path)?,
+            Self::ToolFailed { tool, exit_code, stderr_tail } => {
+                map.serialize_entry("tool", tool)?;
+                map.serialize_entry("exit_code", exit_code)?;
+                map.serialize_entry("stderr_tail", stderr_tail)?;
+            }
+            Self::InvalidUrl { url } => map.serialize_entry("url", url)?,
+            Self::Cancelled { job_id } => map.serialize_entry("job_id", job_id)?,
+            _ => {}
+        }
+
+        map.end()
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Parse {
+            message: error.to_string(),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use tokio::process::Command;
+
+use crate::error::{AppError, AppResult};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PitchMode {
+    #[default]
+    Varispeed, // Pitch and speed change together, like a turntable
+    PreserveTempo, // Pitch changes, duration stays the same
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PitchEngine {
+    #[default]
+    Auto, // rubberband when available, otherwise atempo
+    Rubberband,
+    Atempo,
+}
+
+// List the filters compiled into the given FFmpeg binary
+pub async fn available_filters(ffmpeg_path: &Path) -> AppResult<HashSet<String>> {
+    list_components(ffmpeg_path, "-filters").await
+}
+
+// Names from `ffmpeg -filters`, `-encoders` and similar listings. Entries look like
+// " ... atempo            A->A       Adjust audio tempo." after a legend of "X = meaning" lines.
+pub async fn list_components(ffmpeg_path: &Path, listing: &str) -> AppResult<HashSet<String>> {
+    let output = Command::new(ffmpeg_path)
+        .args(["-hide_banner", listing])
+        .output()
+        .await
+        .map_err(|e| AppError::io("Failed to execute FFmpeg", e))?;
+
+    if !output.status.success() {
+        return Err(AppError::tool_failed("ffmpeg", output.status, &String::from_utf8_lossy(&output.stderr)));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let components = stdout
+        .lines()
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            let flags = tokens.next()?;
+            let name = tokens.next()?;
+            let is_entry = !flags.ends_with(':') && name != "=" && tokens.next().is_some();
+            is_entry.then(|| name.to_string())
+        })
+        .collect();
+
+    Ok(components)
+}
+
+// atempo only accepts factors between 0.5 and 2.0, so larger changes are split into stages
+pub fn atempo_chain(factor: f64) -> Vec<String> {
+    let mut stages = Vec::new();
+    let mut remaining = factor;
+
+    while remaining > 2.0 {
+        stages.push("atempo=2.0".to_string());
+        remaining /= 2.0;
+    }
+    while remaining < 0.5 {
+        stages.push("atempo=0.5".to_string());
+        remaining /= 0.5;
+    }
+    if (remaining - 1.0).abs() > 1e-9 {
+        stages.push(format!("atempo={:.6}", remaining));
+    }
+
+    stages
+}
+
+pub struct PitchStage {
+    pub filters: Vec<String>,
+    pub applied_ratio: f64, // Ratio actually realised, after rounding to an integer sample rate
+}
+
+// Pitch shift followed by the tempo change. `tempo` is a speed multiplier (0.8 = 80% speed) applied
+// on top of whatever speed change the pitch mode introduces.
+pub fn pitch_filters(
+    mode: PitchMode,
+    engine: PitchEngine,
+    available: &HashSet<String>,
+    pitch_factor: f64,
+    tempo: f64,
+    input_rate: u32,
+    output_rate: u32,
+) -> AppResult<PitchStage> {
+    let has_atempo = available.contains("atempo");
+    let change_tempo = (tempo - 1.0).abs() > 1e-9;
+
+    if change_tempo && !has_atempo {
+        return Err(AppError::unsupported("Changing the tempo needs the atempo filter, but the bundled FFmpeg does not have it"));
+    }
+
+    // Reinterpret the samples at a scaled rate, then resample back so players see a normal rate
+    let shifted_rate = (input_rate as f64 * pitch_factor).round() as u32;
+    let varispeed_ratio = shifted_rate as f64 / input_rate as f64;
+    let varispeed = vec![
+        format!("asetrate={}", shifted_rate),
+        format!("aresample={}", output_rate),
+    ];
+
+    if mode == PitchMode::Varispeed {
+        let mut filters = varispeed;
+        filters.extend(atempo_chain(tempo));
+        return Ok(PitchStage { filters, applied_ratio: varispeed_ratio });
+    }
+
+    let has_rubberband = available.contains("rubberband");
+
+    match engine {
+        PitchEngine::Rubberband if !has_rubberband => {
+            Err(AppError::unsupported("The bundled FFmpeg was built without the rubberband filter"))
+        }
+        PitchEngine::Atempo if !has_atempo => {
+            Err(AppError::unsupported("The bundled FFmpeg was built without the atempo filter"))
+        }
+        PitchEngine::Auto if !has_rubberband && !has_atempo => {
+            Err(AppError::unsupported("Tempo-preserving pitch shift needs the rubberband or atempo filter, but the bundled FFmpeg has neither"))
+        }
+        PitchEngine::Rubberband | PitchEngine::Auto if has_rubberband => {
+            let mut filters = vec![format!("rubberband=pitch={}", pitch_factor)];
+            filters.extend(atempo_chain(tempo));
+            filters.push(format!("aresample={}", output_rate));
+            Ok(PitchStage { filters, applied_ratio: pitch_factor })
+        }
+        _ => {
+            // Undo the speed change introduced by asetrate, folding the tempo change into the same chain
+            let mut filters = varispeed;
+            filters.extend(atempo_chain(tempo / varispeed_ratio));
+            Ok(PitchStage { filters, applied_ratio: varispeed_ratio })
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+use tokio::sync::Notify;
+
+use crate::error::{AppError, AppResult};
+use crate::process_tree::{self, ProcessTree};
+use crate::ProcessingProgress;
+
+pub const PROGRESS_EVENT: &str = "processing-progress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Completed,
+    Skipped,
+    Failed,
+    Cancelled,
+}
+
+pub fn new_job_id() -> String {
+    static NEXT_JOB: AtomicU64 = AtomicU64::new(1);
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+
+    format!("job-{}-{}", millis, NEXT_JOB.fetch_add(1, Ordering::Relaxed))
+}
+
+pub fn emit_progress(app: &AppHandle, job_id: &str, percentage: f32, status: &str, current_file: &str) {
+    let _ = app.emit(PROGRESS_EVENT, ProcessingProgress {
+        job_id: job_id.to_string(),
+        percentage,
+        status: status.to_string(),
+        current_file: Some(current_file.to_string()),
+    });
+}
+
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<CancelState>,
+}
+
+#[derive(Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub async fn cancelled(&self) {
+        loop {
+            // Created before the flag check so a concurrent cancel() cannot be missed
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+// Running jobs by id, kept in managed state so `cancel_job` can reach them
+#[derive(Clone, Default)]
+pub struct JobRegistry {
+    jobs: Arc<Mutex<HashMap<String, CancelToken>>>,
+}
+
+impl JobRegistry {
+    pub fn register(&self, job_id: &str) -> AppResult<JobGuard> {
+        let mut jobs = self.jobs.lock().unwrap();
+        if jobs.contains_key(job_id) {
+            return Err(AppError::invalid_option(format!("Job {} is already running", job_id)));
+        }
+
+        let token = CancelToken::default();
+        jobs.insert(job_id.to_string(), token.clone());
+
+        Ok(JobGuard {
+            registry: self.clone(),
+            job_id: job_id.to_string(),
+            token,
+        })
+    }
+
+    // Cancelling a batch also cancels its items, whose ids are "<batch id>/<n>"
+    pub fn cancel(&self, job_id: &str) -> bool {
+        let child_prefix = format!("{}/", job_id);
+        let jobs = self.jobs.lock().unwrap();
+
+        let mut found = false;
+        for (id, token) in jobs.iter() {
+            if id == job_id || id.starts_with(&child_prefix) {
+                token.cancel();
+                found = true;
+            }
+        }
+
+        found
+    }
+}
+
+// Removes the job from the registry when the command finishes, however it finishes
+pub struct JobGuard {
+    registry: JobRegistry,
+    pub job_id: String,
+    pub token: CancelToken,
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        self.registry.jobs.lock().unwrap().remove(&self.job_id);
+    }
+}
+
+pub enum ProcessOutcome {
+    Exited { status: ExitStatus, stderr: String },
+    Cancelled,
+}
+
+// Spawn a tool, feed each stdout line to `on_line` and kill the child, along with anything it
+// started, if the token is cancelled
+pub async fn run_cancellable(
+    command: Command,
+    tool_name: &str,
+    token: &CancelToken,
+    mut on_line: impl FnMut(&str),
+) -> AppResult<ProcessOutcome> {
+    let mut pending: Vec<u8> = Vec::new();
+    let mut emit_line = |line: &[u8]| {
+        let line = String::from_utf8_lossy(line);
+        on_line(line.trim_end_matches(['\r', '\n']));
+    };
+
+    let outcome = run_cancellable_raw(command, tool_name, token, |chunk| {
+        pending.extend_from_slice(chunk);
+        while let Some(end) = pending.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            emit_line(&line);
+        }
+    })
+    .await?;
+
+    if !pending.is_empty() && matches!(outcome, ProcessOutcome::Exited { .. }) {
+        emit_line(&pending);
+    }
+
+    Ok(outcome)
+}
+
+// Like `run_cancellable`, but stdout is handed to `on_chunk` as raw bytes, e.g. decoded PCM
+pub async fn run_cancellable_raw(
+    mut command: Command,
+    tool_name: &str,
+    token: &CancelToken,
+    mut on_chunk: impl FnMut(&[u8]),
+) -> AppResult<ProcessOutcome> {
+    if token.is_cancelled() {
+        return Ok(ProcessOutcome::Cancelled);
+    }
+
+    process_tree::isolate(&mut command);
+
+    // Like `.output()`, keep the child off the terminal so FFmpeg cannot read keystrokes from it
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| AppError::io(&format!("Failed to execute {}", tool_name), e))?;
+    let tree = ProcessTree::attach(&child);
+
+    let capture_error = || AppError::io(&format!("Failed to capture {} output", tool_name), "pipe unavailable");
+    let mut stdout = child.stdout.take().ok_or_else(capture_error)?;
+    let mut stderr = child.stderr.take().ok_or_else(capture_error)?;
+
+    // Drain stderr concurrently so the child never blocks on a full pipe
+    let stderr_task = tokio::spawn(async move {
+        let mut buffer = String::new();
+        let _ = stderr.read_to_string(&mut buffer).await;
+        buffer
+    });
+
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        tokio::select! {
+            _ = token.cancelled() => {
+                tree.kill();
+                let _ = child.kill().await;
+                return Ok(ProcessOutcome::Cancelled);
+            }
+            read = stdout.read(&mut buffer) => {
+                match read.map_err(|e| AppError::io(&format!("Failed to read {} output", tool_name), e))? {
+                    0 => break,
+                    length => on_chunk(&buffer[..length]),
+                }
+            }
+        }
+    }
+
+    let status = tokio::select! {
+        _ = token.cancelled() => {
+            tree.kill();
+            let _ = child.kill().await;
+            return Ok(ProcessOutcome::Cancelled);
+        }
+        status = child.wait() => status.map_err(|e| AppError::io(&format!("Failed to wait for {}", tool_name), e))?,
+    };
+    let stderr = stderr_task.await.unwrap_or_default();
+
+    Ok(ProcessOutcome::Exited { status, stderr })
+}
+
+// Run FFmpeg with machine-readable progress on stdout, emitting events as output time advances.
+// `expected_duration` is the length of the output in seconds, used to turn time into a percentage
+// within `span`, so multi-pass jobs can report each pass as part of one 0-100 range.
+// Returns FFmpeg's stderr, which is where filters such as loudnorm print their reports.
+pub async fn run_ffmpeg_with_progress(
+    app: &AppHandle,
+    job: &JobGuard,
+    ffmpeg_path: &Path,
+    args: &[String],
+    expected_duration: Option<f64>,
+    span: Range<f32>,
+    current_file: &str,
+) -> AppResult<String> {
+    let mut command = Command::new(ffmpeg_path);
+    command
+        .args(["-hide_banner", "-nostdin", "-nostats", "-progress", "pipe:1"])
+        .args(args);
+
+    let job_id = job.job_id.as_str();
+    let outcome = run_cancellable(command, "ffmpeg", &job.token, |line| {
+        // Despite the name, FFmpeg reports out_time_ms in microseconds
+        let Some(value) = line.strip_prefix("out_time_ms=") else {
+            return;
+        };
+        let (Ok(micros), Some(total)) = (value.trim().parse::<f64>(), expected_duration) else {
+            return;
+        };
+        if total > 0.0 {
+            let fraction = (micros / 1_000_000.0 / total).clamp(0.0, 0.999) as f32;
+            let percentage = span.start + fraction * (span.end - span.start);
+            emit_progress(app, job_id, percentage, "processing", current_file);
+        }
+    })
+    .await?;
+
+    match outcome {
+        ProcessOutcome::Cancelled => Err(AppError::Cancelled {
+            job_id: job_id.to_string(),
+        }),
+        ProcessOutcome::Exited { status, stderr } if !status.success() => {
+            Err(AppError::tool_failed("ffmpeg", status, &stderr))
+        }
+        ProcessOutcome::Exited { stderr, .. } => Ok(stderr),
+    }
+}
@@ -0,0 +1,371 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::path::Path;
+use tokio::process::Command;
+
+use crate::error::{AppError, AppResult};
+
+pub const PITCH_CLASSES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+// Audio is decoded to mono at a low rate; nothing above ~2 kHz is needed for chroma
+const SAMPLE_RATE: u32 = 11025;
+const FRAME_SIZE: usize = 4096;
+const HOP_SIZE: usize = 2048;
+const MAX_ANALYSIS_SECONDS: u32 = 240;
+const MIN_FREQUENCY: f64 = 55.0;
+const MAX_FREQUENCY: f64 = 2000.0;
+
+// Krumhansl-Kessler key profiles, starting from the tonic
+const MAJOR_PROFILE: [f64; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+const MINOR_PROFILE: [f64; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyMode {
+    Major,
+    Minor,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyEstimate {
+    pub key: String, // Tonic name, e.g. "F#"
+    pub tonic: u8, // Pitch class of the tonic, C = 0
+    pub mode: KeyMode,
+    pub label: String, // e.g. "F# minor"
+    pub confidence: f32, // Correlation with the best matching key profile, 0 to 1
+}
+
+pub async fn detect_key(ffmpeg_path: &Path, file_path: &str) -> AppResult<KeyEstimate> {
+    let samples = decode_mono_pcm(ffmpeg_path, file_path).await?;
+
+    if samples.len() < FRAME_SIZE {
+        return Err(AppError::Analysis {
+            message: "Audio is too short to detect a key".to_string(),
+        });
+    }
+
+    // The FFT work is CPU bound, keep it off the async runtime
+    let chroma = tokio::task::spawn_blocking(move || chromagram(&samples))
+        .await
+        .map_err(|e| AppError::Analysis {
+            message: format!("Key detection failed: {}", e),
+        })?;
+
+    estimate_key(&chroma).ok_or_else(|| AppError::Analysis {
+        message: "No tonal content found to detect a key".to_string(),
+    })
+}
+
+async fn decode_mono_pcm(ffmpeg_path: &Path, file_path: &str) -> AppResult<Vec<f32>> {
+    let sample_rate = SAMPLE_RATE.to_string();
+    let max_seconds = MAX_ANALYSIS_SECONDS.to_string();
+
+    let output = Command::new(ffmpeg_path)
+        .args([
+            "-v", "error",
+            "-i", file_path,
+            "-t", &max_seconds,
+            "-ac", "1",
+            "-ar", &sample_rate,
+            "-f", "f32le",
+            "pipe:1",
+        ])
+        .output()
+        .await
+        .map_err(|e| AppError::io("Failed to execute FFmpeg", e))?;
+
+    if !output.status.success() {
+        return Err(AppError::tool_failed("ffmpeg", output.status, &String::from_utf8_lossy(&output.stderr)));
+    }
+
+    Ok(output
+        .stdout
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+// Sum of per-frame pitch class energy, each frame normalised so loud passages don't dominate
+fn chromagram(samples: &[f32]) -> [f64; 12] {
+    let window: Vec<f64> = (0..FRAME_SIZE)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / FRAME_SIZE as f64).cos())
+        .collect();
+
+    // Pitch class of every FFT bin inside the analysed range
+    let bin_classes: Vec<Option<usize>> = (0..FRAME_SIZE / 2)
+        .map(|bin| {
+            let frequency = bin as f64 * SAMPLE_RATE as f64 / FRAME_SIZE as f64;
+            if !(MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+                return None;
+            }
+            let midi = 69.0 + 12.0 * (frequency / 440.0).log2();
+            Some((midi.round() as i64).rem_euclid(12) as usize)
+        })
+        .collect();
+
+    let mut chroma = [0.0; 12];
+    let mut re = vec![0.0; FRAME_SIZE];
+    let mut im = vec![0.0; FRAME_SIZE];
+
+    for start in (0..=samples.len() - FRAME_SIZE).step_by(HOP_SIZE) {
+        for i in 0..FRAME_SIZE {
+            re[i] = samples[start + i] as f64 * window[i];
+            im[i] = 0.0;
+        }
+        fft(&mut re, &mut im);
+
+        let mut frame = [0.0; 12];
+        for (bin, class) in bin_classes.iter().enumerate() {
+            if let Some(class) = class {
+                frame[*class] += (re[bin] * re[bin] + im[bin] * im[bin]).sqrt();
+            }
+        }
+
+        let total: f64 = frame.iter().sum();
+        if total > 1e-6 {
+            for (sum, value) in chroma.iter_mut().zip(frame) {
+                *sum += value / total;
+            }
+        }
+    }
+
+    chroma
+}
+
+// In-place iterative radix-2 FFT, the length must be a power of two
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+fn correlation(a: &[f64; 12], b: &[f64; 12]) -> f64 {
+    let mean_a = a.iter().sum::<f64>() / 12.0;
+    let mean_b = b.iter().sum::<f64>() / 12.0;
+
+    let mut covariance = 0.0;
+    let mut variance_a = 0.0;
+    let mut variance_b = 0.0;
+    for i in 0..12 {
+        let da = a[i] - mean_a;
+        let db = b[i] - mean_b;
+        covariance += da * db;
+        variance_a += da * da;
+        variance_b += db * db;
+    }
+
+    if variance_a <= 0.0 || variance_b <= 0.0 {
+        return 0.0;
+    }
+    covariance / (variance_a * variance_b).sqrt()
+}
+
+// Match the chromagram against all 24 rotated major/minor profiles
+fn estimate_key(chroma: &[f64; 12]) -> Option<KeyEstimate> {
+    if chroma.iter().all(|value| *value <= 0.0) {
+        return None;
+    }
+
+    let mut best: Option<(u8, KeyMode, f64)> = None;
+
+    for tonic in 0..12 {
+        for (mode, profile) in [(KeyMode::Major, &MAJOR_PROFILE), (KeyMode::Minor, &MINOR_PROFILE)] {
+            let mut rotated = [0.0; 12];
+            for (class, value) in rotated.iter_mut().enumerate() {
+                *value = profile[(class + 12 - tonic) % 12];
+            }
+
+            let score = correlation(chroma, &rotated);
+            if best.is_none_or(|(_, _, best_score)| score > best_score) {
+                best = Some((tonic as u8, mode, score));
+            }
+        }
+    }
+
+    best.map(|(tonic, mode, score)| KeyEstimate {
+        key: PITCH_CLASSES[tonic as usize].to_string(),
+        tonic,
+        mode,
+        label: key_label(tonic, mode),
+        confidence: score.clamp(0.0, 1.0) as f32,
+    })
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransposeDirection {
+    #[default]
+    Nearest, // -5 to +6 semitones
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KeyTarget {
+    // Move the song into a named key, e.g. "G major", "F#m" or "Bb"
+    Key {
+        key: String,
+        #[serde(default)]
+        source_key: Option<String>, // Detected from the audio when missing
+        #[serde(default)]
+        direction: TransposeDirection,
+    },
+    // Move the song so its highest melody note lands on the top of a singer's range
+    Range {
+        highest_note: String, // e.g. "E5"
+        range_top: String, // e.g. "C5"
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyShift {
+    pub source_key: Option<String>,
+    pub target_key: Option<String>,
+    pub semitones: i32,
+}
+
+// Parse a key name such as "G", "g minor", "F#m", "Bb major" or "E♭m"
+pub fn parse_key(name: &str) -> AppResult<(u8, KeyMode)> {
+    let unrecognised = || AppError::invalid_option(format!("Unrecognised key: {}", name));
+    let (tonic, rest) = parse_pitch_class(name.trim()).ok_or_else(unrecognised)?;
+
+    let mode = match rest.trim().to_lowercase().as_str() {
+        "" | "maj" | "major" => KeyMode::Major,
+        "m" | "min" | "minor" => KeyMode::Minor,
+        _ => return Err(unrecognised()),
+    };
+
+    Ok((tonic, mode))
+}
+
+// Parse a note with octave such as "E5" or "Bb3" into a MIDI note number
+pub fn parse_note(name: &str) -> AppResult<i32> {
+    let (class, rest) = parse_pitch_class(name.trim())
+        .ok_or_else(|| AppError::invalid_option(format!("Unrecognised note: {}", name)))?;
+    let octave = rest
+        .trim()
+        .parse::<i32>()
+        .map_err(|_| AppError::invalid_option(format!("Note is missing an octave: {}", name)))?;
+
+    Ok((octave + 1) * 12 + class as i32)
+}
+
+// Split a leading note letter and accidentals off a string, returning the pitch class and the remainder
+fn parse_pitch_class(text: &str) -> Option<(u8, &str)> {
+    let mut chars = text.char_indices();
+    let (_, letter) = chars.next()?;
+    let base: i32 = match letter.to_ascii_uppercase() {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+
+    let mut offset = 0;
+    let mut rest = &text[letter.len_utf8()..];
+    for (index, c) in chars {
+        match c {
+            '#' | '♯' => offset += 1,
+            'b' | '♭' => offset -= 1,
+            _ => {
+                rest = &text[index..];
+                break;
+            }
+        }
+        rest = &text[index + c.len_utf8()..];
+    }
+
+    Some(((base + offset).rem_euclid(12) as u8, rest))
+}
+
+fn key_label(tonic: u8, mode: KeyMode) -> String {
+    match mode {
+        KeyMode::Major => format!("{} major", PITCH_CLASSES[tonic as usize]),
+        KeyMode::Minor => format!("{} minor", PITCH_CLASSES[tonic as usize]),
+    }
+}
+
+pub async fn resolve_key_shift(
+    ffmpeg_path: &Path,
+    file_path: &str,
+    target: &KeyTarget,
+) -> AppResult<KeyShift> {
+    match target {
+        KeyTarget::Key { key, source_key, direction } => {
+            let (target_tonic, target_mode) = parse_key(key)?;
+            let (source_tonic, source_mode) = match source_key {
+                Some(source_key) => parse_key(source_key)?,
+                None => {
+                    let estimate = detect_key(ffmpeg_path, file_path).await?;
+                    (estimate.tonic, estimate.mode)
+                }
+            };
+
+            // A target in the other mode is read as its relative key, so "C major" for an A minor song means A minor
+            let target_tonic = match (source_mode, target_mode) {
+                (KeyMode::Major, KeyMode::Minor) => (target_tonic + 3) % 12,
+                (KeyMode::Minor, KeyMode::Major) => (target_tonic + 9) % 12,
+                _ => target_tonic,
+            };
+
+            let up = (target_tonic as i32 - source_tonic as i32).rem_euclid(12);
+            let semitones = match direction {
+                TransposeDirection::Nearest if up > 6 => up - 12,
+                TransposeDirection::Nearest | TransposeDirection::Up => up,
+                TransposeDirection::Down if up == 0 => 0,
+                TransposeDirection::Down => up - 12,
+            };
+
+            Ok(KeyShift {
+                source_key: Some(key_label(source_tonic, source_mode)),
+                target_key: Some(key_label(target_tonic, source_mode)),
+                semitones,
+            })
+        }
+        KeyTarget::Range { highest_note, range_top } => {
+            let semitones = parse_note(range_top)? - parse_note(highest_note)?;
+
+            Ok(KeyShift {
+                source_key: None,
+                target_key: None,
+                semitones,
+            })
+        }
+    }
+}
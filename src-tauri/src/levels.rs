@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::process::Command;
+
+use crate::error::{AppError, AppResult};
+use crate::jobs::{self, JobGuard, ProcessOutcome};
+
+// Brick-wall limiter at about -1 dBFS, enough headroom for lossy encoders that overshoot slightly
+pub const SAFETY_LIMITER: &str = "alimiter=limit=0.891:level=false";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelAnalysis {
+    pub peak_db: f64, // dBFS, above 0 means the decoded output exceeds full scale
+    pub rms_db: f64,
+    pub clipped_samples: u64, // Decoded samples, over all channels, with |x| >= 1.0
+    pub clipping: bool,
+}
+
+// Counts full-scale samples in f32le PCM that arrives in arbitrary chunks
+#[derive(Default)]
+struct OverCounter {
+    carry: Vec<u8>, // Bytes of a sample split across two chunks
+    overs: u64,
+}
+
+impl OverCounter {
+    fn feed(&mut self, chunk: &[u8]) {
+        self.carry.extend_from_slice(chunk);
+        let whole = self.carry.len() / 4 * 4;
+
+        self.overs += self.carry[..whole]
+            .chunks_exact(4)
+            .filter(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]).abs() >= 1.0)
+            .count() as u64;
+        self.carry.drain(..whole);
+    }
+}
+
+// Last "<label>: <value>" in the given astats section
+fn find_stat(section: &str, label: &str) -> Option<String> {
+    section
+        .lines()
+        .rev()
+        .find_map(|line| line.split_once(&format!("] {}:", label)).map(|(_, value)| value.trim().to_string()))
+}
+
+// astats prints one block per channel and finally an "Overall" block, which is the one we want
+fn parse_levels(stderr: &str, clipped_samples: u64) -> AppResult<LevelAnalysis> {
+    let overall = stderr
+        .rfind("] Overall")
+        .map(|index| &stderr[index..])
+        .ok_or_else(|| AppError::parse("Failed to parse astats output", "no overall statistics found"))?;
+
+    let number = |label: &str| -> AppResult<f64> {
+        find_stat(overall, label)
+            .ok_or_else(|| AppError::parse("Failed to parse astats output", format!("missing {}", label)))?
+            .parse::<f64>()
+            .map_err(|e| AppError::parse(&format!("Failed to parse astats {}", label), e))
+    };
+
+    Ok(LevelAnalysis {
+        peak_db: number("Peak level dB")?,
+        rms_db: number("RMS level dB")?,
+        clipped_samples,
+        clipping: clipped_samples > 0,
+    })
+}
+
+// Decode the finished file to float and measure its peak and RMS levels. astats reports on stderr
+// while the decoded samples come back on stdout, where full-scale samples are counted as clipped.
+pub async fn analyze_levels(ffmpeg_path: &Path, file_path: &Path, job: &JobGuard) -> AppResult<LevelAnalysis> {
+    let mut command = Command::new(ffmpeg_path);
+    command
+        .args(["-hide_banner", "-nostdin", "-nostats", "-i"])
+        .arg(file_path)
+        .args(["-map", "0:a:0", "-af", "aformat=sample_fmts=flt,astats", "-f", "f32le", "pipe:1"]);
+
+    let mut counter = OverCounter::default();
+    match jobs::run_cancellable_raw(command, "ffmpeg", &job.token, |chunk| counter.feed(chunk)).await? {
+        ProcessOutcome::Cancelled => Err(AppError::Cancelled {
+            job_id: job.job_id.clone(),
+        }),
+        ProcessOutcome::Exited { status, stderr } if !status.success() => {
+            Err(AppError::tool_failed("ffmpeg", status, &stderr))
+        }
+        ProcessOutcome::Exited { stderr, .. } => parse_levels(&stderr, counter.overs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // astats stderr for a stereo file, per-channel and overall blocks trimmed to a few statistics
+    const ASTATS_STDERR: &str = "\
+Input #0, wav, from 'song.wav':
+  Duration: 00:00:10.00, bitrate: 1411 kb/s
+  Stream #0:0: Audio: pcm_s16le ([1][0][0][0] / 0x0001), 44100 Hz, stereo, s16, 1411 kb/s
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Channel: 1
+[Parsed_astats_1 @ 0x5603c5b0a3c0] DC offset: -0.000031
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Min level: -0.965393
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Max level: 0.966492
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Peak level dB: -0.296541
+[Parsed_astats_1 @ 0x5603c5b0a3c0] RMS level dB: -13.912784
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Peak count: 2
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Channel: 2
+[Parsed_astats_1 @ 0x5603c5b0a3c0] DC offset: 0.000012
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Min level: -0.941986
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Max level: 0.953278
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Peak level dB: -0.416134
+[Parsed_astats_1 @ 0x5603c5b0a3c0] RMS level dB: -14.204357
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Peak count: 1
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Overall
+[Parsed_astats_1 @ 0x5603c5b0a3c0] DC offset: -0.000010
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Min level: -0.965393
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Max level: 0.966492
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Peak level dB: -0.296541
+[Parsed_astats_1 @ 0x5603c5b0a3c0] RMS level dB: -14.056522
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Peak count: 3
+[Parsed_astats_1 @ 0x5603c5b0a3c0] Number of samples: 441000
+";
+
+    #[test]
+    fn reads_the_overall_block() {
+        let levels = parse_levels(ASTATS_STDERR, 0).unwrap();
+
+        assert_eq!(levels.peak_db, -0.296541);
+        assert_eq!(levels.rms_db, -14.056522);
+        assert_eq!(levels.clipped_samples, 0);
+        assert!(!levels.clipping);
+    }
+
+    #[test]
+    fn clipping_follows_the_counted_overs() {
+        let levels = parse_levels(ASTATS_STDERR, 12).unwrap();
+
+        assert_eq!(levels.clipped_samples, 12);
+        assert!(levels.clipping);
+    }
+
+    #[test]
+    fn missing_overall_block_is_an_error() {
+        let channel_only = &ASTATS_STDERR[..ASTATS_STDERR.find("Overall").unwrap()];
+
+        assert!(parse_levels(channel_only, 0).is_err());
+    }
+
+    #[test]
+    fn counts_overs_split_across_chunks() {
+        let bytes: Vec<u8> = [0.5_f32, 1.0, -1.25, 0.999, -1.0]
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+
+        let mut counter = OverCounter::default();
+        for chunk in bytes.chunks(3) {
+            counter.feed(chunk);
+        }
+
+        assert_eq!(counter.overs, 3);
+        assert!(counter.carry.is_empty());
+    }
+}
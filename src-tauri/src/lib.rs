@@ -1,7 +1,42 @@
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use tokio::process::Command; // Use tokio::process::Command for async operations
-use tauri::{Listener, Emitter}; // Import Listener trait for listening to events
+use tauri::{AppHandle, Listener, Emitter, Manager}; // Import Listener trait for listening to events
+
+mod batch;
+mod download;
+mod error;
+mod filters;
+mod jobs;
+mod key_detection;
+mod levels;
+mod loudness;
+mod metadata;
+mod naming;
+mod playlist;
+mod probe;
+mod process_tree;
+mod profile;
+mod section;
+mod staging;
+mod tools;
+mod vocals;
+
+pub use batch::{BatchConversionOptions, BatchConversionResult, BatchItemResult, BatchSummary};
+pub use download::{DownloadFormat, DownloadPhase, DownloadProgress, ExtractorAllowList, MediaFormat, MediaInfo};
+pub use error::{AppError, AppResult};
+pub use filters::{PitchEngine, PitchMode};
+pub use jobs::{JobRegistry, JobStatus};
+pub use key_detection::{KeyEstimate, KeyMode, KeyShift, KeyTarget, TransposeDirection};
+pub use levels::LevelAnalysis;
+pub use loudness::{LoudnessMeasurement, LoudnessOptions, LoudnessReport};
+pub use metadata::MetadataOptions;
+pub use naming::CollisionPolicy;
+pub use playlist::{PlaylistDownloadResult, PlaylistEntry, PlaylistInfo, PlaylistItemResult};
+pub use probe::{AudioStreamInfo, AudioTags};
+pub use profile::{OutputFormat, OutputProfile};
+pub use section::{SectionOptions, TimeValue};
+pub use tools::{Tool, ToolLocator, ToolSource, ToolStatus};
+pub use vocals::VocalReduction;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AudioFile {
@@ -10,24 +45,122 @@ pub struct AudioFile {
     pub size: u64,
     pub duration: Option<f64>,
     pub format: Option<String>,
+    #[serde(default)]
+    pub stream: Option<AudioStreamInfo>, // Codec, sample rate, channels, tags etc. from FFprobe
+    #[serde(default)]
+    pub key: Option<KeyEstimate>, // Only filled in when key detection was requested
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConversionOptions {
-    pub semitones: i32, // Positive for up, negative for down
-    pub output_format: String, // mp3, wav, etc.
-    pub output_path: String,
+    pub semitones: f64, // Positive for up, negative for down, fractions allowed
+    #[serde(default)]
+    pub cents: f64, // Added on top of semitones, -100 to 100
+    #[serde(default)]
+    pub reference_pitch: Option<ReferencePitch>,
+    #[serde(default)]
+    pub key_target: Option<KeyTarget>, // Replaces `semitones` with the interval to the target
+    #[serde(alias = "output_format")]
+    pub output: OutputProfile, // "mp3", "wav", ... or a full profile with codec and quality
+    #[serde(default)]
+    pub output_path: String, // Exact output file, used when output_dir is not set
+    #[serde(default)]
+    pub output_dir: Option<String>,
+    #[serde(default)]
+    pub filename_template: Option<String>, // e.g. "{stem} [{semitones:+}]{ext}", used with output_dir
+    #[serde(default)]
+    pub collision: CollisionPolicy,
+    #[serde(default)]
+    pub output_sample_rate: Option<u32>, // None keeps the input sample rate
+    #[serde(default)]
+    pub pitch_mode: PitchMode,
+    #[serde(default)]
+    pub pitch_engine: PitchEngine, // Only used by PitchMode::PreserveTempo
+    #[serde(default = "default_tempo")]
+    pub tempo: f64, // Speed multiplier at unchanged pitch, e.g. 0.8 for an 80% practice track
+    #[serde(default)]
+    pub vocal_reduction: Option<VocalReduction>, // Karaoke mode, stereo inputs only
+    #[serde(default)]
+    pub section: SectionOptions, // Trim, fade and loop, the default exports the whole file
+    #[serde(default)]
+    pub normalize: Option<LoudnessOptions>, // Two-pass EBU R128 loudness normalization
+    #[serde(default)]
+    pub auto_limit: bool, // Re-render through a safety limiter when the output clips
+    #[serde(default)]
+    pub metadata: MetadataOptions,
+}
+
+// Retune between concert pitch standards, e.g. A4 = 432 Hz to A4 = 440 Hz
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferencePitch {
+    #[serde(default = "default_reference_hz")]
+    pub source_hz: f64,
+    pub target_hz: f64,
+}
+
+fn default_reference_hz() -> f64 {
+    440.0
+}
+
+fn default_tempo() -> f64 {
+    1.0
+}
+
+impl ConversionOptions {
+    // Total shift in semitones from the semitone, cents and reference pitch settings
+    fn total_semitones(&self) -> AppResult<f64> {
+        if !self.semitones.is_finite() || self.semitones.abs() > 24.0 {
+            return Err(AppError::invalid_option(format!("Semitone shift must be between -24 and 24, got {}", self.semitones)));
+        }
+        if !self.cents.is_finite() || self.cents.abs() > 100.0 {
+            return Err(AppError::invalid_option(format!("Cents offset must be between -100 and 100, got {}", self.cents)));
+        }
+        
+        let mut total = self.semitones + self.cents / 100.0;
+        
+        if let Some(reference) = &self.reference_pitch {
+            for hz in [reference.source_hz, reference.target_hz] {
+                if !(400.0..=480.0).contains(&hz) {
+                    return Err(AppError::invalid_option(format!("Reference pitch must be between 400 and 480 Hz, got {}", hz)));
+                }
+            }
+            total += 12.0 * (reference.target_hz / reference.source_hz).log2();
+        }
+        
+        Ok(total)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+pub struct ConversionResult {
+    pub job_id: String,
+    #[serde(default)]
+    pub skipped: bool, // The output already existed and the collision policy was Skip
+    pub message: String,
+    pub output_path: String,
+    pub semitones: f64, // Requested shift including cents and reference pitch
+    pub pitch_ratio: f64, // Frequency ratio actually applied by the filter graph
+    #[serde(default)]
+    pub tempo: f64,
+    #[serde(default)]
+    pub output_duration: Option<f64>, // Seconds, after any speed or tempo change
+    #[serde(default)]
+    pub loudness: Option<LoudnessReport>, // Measured before and after values when normalizing
+    #[serde(default)]
+    pub levels: Option<LevelAnalysis>, // Peak and clipping analysis of the finished output
+    #[serde(default)]
+    pub limiter_applied: bool,
+    pub key_shift: Option<KeyShift>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessingProgress {
+    pub job_id: String,
     pub percentage: f32,
     pub status: String,
     pub current_file: Option<String>,
 }
 
-// Get the path to bundled FFmpeg binary
-
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -36,211 +169,415 @@ fn greet(name: &str) -> String {
 
 #[tauri::command]
 async fn process_audio_file(
+    app: AppHandle,
     file_path: String,
     options: ConversionOptions,
-) -> Result<String, String> {
+    job_id: Option<String>, // Supplied by the caller to match progress events, generated otherwise
+) -> AppResult<ConversionResult> {
+    let job_id = job_id.unwrap_or_else(jobs::new_job_id);
+    convert_file(&app, job_id, file_path, options).await
+}
+
+#[tauri::command]
+async fn process_audio_batch(
+    app: AppHandle,
+    file_paths: Vec<String>,
+    options: BatchConversionOptions,
+    job_id: Option<String>,
+) -> AppResult<BatchConversionResult> {
+    let job_id = job_id.unwrap_or_else(jobs::new_job_id);
+    batch::run_batch(app, job_id, file_paths, options).await
+}
+
+async fn convert_file(
+    app: &AppHandle,
+    job_id: String,
+    file_path: String,
+    mut options: ConversionOptions,
+) -> AppResult<ConversionResult> {
     let input_path = Path::new(&file_path);
     
     if !input_path.exists() {
-        return Err("Input file does not exist".to_string());
+        return Err(AppError::InputNotFound { path: file_path });
     }
     
-    let ffmpeg_path = get_bundled_ffmpeg_path()?;
-    let pitch_factor = 2.0_f64.powf(options.semitones as f64 / 12.0);
+    if let Some(rate) = options.output_sample_rate {
+        if !(8000..=384000).contains(&rate) {
+            return Err(AppError::invalid_option(format!("Unsupported output sample rate: {} Hz", rate)));
+        }
+    }
     
-    let output = Command::new(ffmpeg_path)
-        .args([
-            "-i", &file_path,
-            "-af", &format!("asetrate=44100*{},aresample=44100", pitch_factor),
-            "-f", &options.output_format,
-            "-y", &options.output_path
-        ])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to execute FFmpeg: {}", e))?;
+    if !options.tempo.is_finite() || !(0.25..=4.0).contains(&options.tempo) {
+        return Err(AppError::invalid_option(format!("Tempo must be between 0.25 and 4.0, got {}", options.tempo)));
+    }
+    let change_tempo = (options.tempo - 1.0).abs() > 1e-9;
     
-    if !output.status.success() {
-        return Err(format!("FFmpeg error: {}", String::from_utf8_lossy(&output.stderr)));
+    let tools = app.state::<ToolLocator>();
+    let ffmpeg_path = tools.resolve(Tool::Ffmpeg)?;
+    
+    let key_shift = match &options.key_target {
+        Some(target) => Some(key_detection::resolve_key_shift(&ffmpeg_path, &file_path, target).await?),
+        None => None,
+    };
+    if let Some(shift) = &key_shift {
+        options.semitones = shift.semitones as f64;
     }
     
-    Ok(format!("Successfully processed {} with {} semitones shift", 
-               input_path.file_name().unwrap_or_default().to_string_lossy(),
-               options.semitones))
-}
-
-fn get_bundled_ffmpeg_path() -> Result<PathBuf, String> {
-    let mut exe_dir = std::env::current_exe()
-        .map_err(|e| format!("Failed to get executable directory: {}", e))?;
-    exe_dir.pop(); // Remove executable name
+    let semitones = options.total_semitones()?;
+    if let Some(normalize) = &options.normalize {
+        normalize.validate()?;
+    }
+    let encoders = filters::list_components(&ffmpeg_path, "-encoders").await?;
+    options.output.validate(&encoders)?;
+    
+    let stream = probe::probe_audio(&tools.resolve(Tool::Ffprobe)?, &file_path).await?;
+    let pitch_factor = 2.0_f64.powf(semitones / 12.0);
+    let output_rate = options.output.output_sample_rate(options.output_sample_rate.unwrap_or(stream.sample_rate));
     
-    #[cfg(target_os = "windows")]
-    let ffmpeg_name = "ffmpeg.exe";
-    #[cfg(not(target_os = "windows"))]
-    let ffmpeg_name = "ffmpeg";
+    let available = if options.pitch_mode == PitchMode::PreserveTempo || change_tempo {
+        filters::available_filters(&ffmpeg_path).await?
+    } else {
+        Default::default()
+    };
+    let pitch = filters::pitch_filters(
+        options.pitch_mode,
+        options.pitch_engine,
+        &available,
+        pitch_factor,
+        options.tempo,
+        stream.sample_rate,
+        output_rate,
+    )?;
     
-    let ffmpeg_path = exe_dir.join(ffmpeg_name);
+    // Varispeed output is shorter or longer than the input by the pitch ratio, the tempo applies on top
+    let speed = match options.pitch_mode {
+        PitchMode::Varispeed => pitch.applied_ratio * options.tempo,
+        PitchMode::PreserveTempo => options.tempo,
+    };
     
-    if !ffmpeg_path.exists() {
-        return Err("FFmpeg binary not found in application directory".to_string());
+    // Trim in input time, then fade and loop in output time so the section lines up after the tempo change
+    let (start, end) = options.section.input_range(stream.duration)?;
+    let section_length = end.or(stream.duration).map(|end| (end - start) / speed);
+    let mut shift_filters = section::trim_filters(start, end);
+    if let Some(vocal_reduction) = &options.vocal_reduction {
+        shift_filters.push(vocal_reduction.filters(stream.channels, stream.sample_rate)?);
     }
+    shift_filters.extend(pitch.filters.iter().cloned());
+    let section_filters = options.section.output_filters(section_length, output_rate)?;
+    let output_duration = section_length.map(|length| length * options.section.loop_count as f64);
     
-    Ok(ffmpeg_path)
-}
-
-fn get_bundled_ffprobe_path() -> Result<PathBuf, String> {
-    let mut exe_dir = std::env::current_exe()
-        .map_err(|e| format!("Failed to get executable directory: {}", e))?;
-    exe_dir.pop(); // Remove executable name
+    let file_name = input_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let file_stem = input_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let target_key = key_shift.as_ref().and_then(|shift| shift.target_key.as_deref());
+    
+    let candidate = match &options.output_dir {
+        Some(output_dir) => {
+            std::fs::create_dir_all(output_dir)
+                .map_err(|e| AppError::io("Failed to create output directory", e))?;
+            
+            let template = options.filename_template.as_deref().unwrap_or(naming::DEFAULT_TEMPLATE);
+            let output_name = naming::render_template(template, &naming::NameContext {
+                stem: &file_stem,
+                extension: options.output.format.extension(),
+                semitones,
+                key: target_key,
+                title: stream.tags.title.as_deref(),
+            })?;
+            Path::new(output_dir).join(output_name)
+        }
+        None if !options.output_path.is_empty() => PathBuf::from(&options.output_path),
+        None => return Err(AppError::invalid_option("Either output_path or output_dir must be set")),
+    };
+    
+    let reservation = match naming::claim_output(candidate, options.collision)? {
+        naming::OutputTarget::Write(reservation) => reservation,
+        naming::OutputTarget::Skip(existing) => {
+            return Ok(ConversionResult {
+                message: format!("Skipped {}, {} already exists", file_name, existing.display()),
+                job_id,
+                skipped: true,
+                output_path: existing.to_string_lossy().to_string(),
+                semitones,
+                pitch_ratio: pitch.applied_ratio,
+                tempo: options.tempo,
+                output_duration,
+                loudness: None,
+                levels: None,
+                limiter_applied: false,
+                key_shift,
+            });
+        }
+    };
+    let output_path = reservation.path.to_string_lossy().to_string();
+    
+    // FFmpeg writes to a hidden temp file that only replaces the output once it has finished
+    let staged = staging::StagedOutput::new(&app.state::<staging::Journal>(), &reservation.path);
     
-    #[cfg(target_os = "windows")]
-    let ffprobe_name = "ffprobe.exe";
-    #[cfg(not(target_os = "windows"))]
-    let ffprobe_name = "ffprobe";
+    let job = app.state::<JobRegistry>().register(&job_id)?;
+    jobs::emit_progress(app, &job_id, 0.0, "started", &file_name);
+    
+    let rendered: AppResult<_> = async {
+        // Loudness is measured on the shifted audio, before fades and repeats
+        let measured = match &options.normalize {
+            Some(normalize) => {
+                let mut measure_filters = shift_filters.clone();
+                measure_filters.push(normalize.measure_filter());
+                let measure_args: Vec<String> = vec![
+                    "-i".into(), file_path.clone(),
+                    "-map".into(), "0:a:0".into(),
+                    "-af".into(), measure_filters.join(","),
+                    "-f".into(), "null".into(), "-".into(),
+                ];
+                let stderr = jobs::run_ffmpeg_with_progress(app, &job, &ffmpeg_path, &measure_args, section_length, 0.0..45.0, &file_name).await?;
+                Some(loudness::parse_stats(&stderr)?)
+            }
+            None => None,
+        };
+        
+        let mut filter_chain = shift_filters.clone();
+        if let (Some(normalize), Some(measured)) = (&options.normalize, &measured) {
+            // loudnorm upsamples to 192 kHz internally
+            filter_chain.push(normalize.apply_filter(measured));
+            filter_chain.push(format!("aresample={}", output_rate));
+        }
+        filter_chain.extend(section_filters.iter().cloned());
+        
+        // Render, check the result for clipping and, if allowed, render once more through a limiter
+        let mut limiter_applied = false;
+        loop {
+            let mut args: Vec<String> = vec!["-i".into(), file_path.clone()];
+            args.extend(metadata::metadata_args(
+                &options.metadata,
+                &stream,
+                options.output.format.muxer(),
+                &file_stem,
+                semitones,
+                target_key,
+            ));
+            args.extend(["-af".into(), filter_chain.join(",")]);
+            args.extend(options.output.codec_args());
+            args.extend([
+                "-f".into(), options.output.format.muxer().to_string(),
+                "-y".into(), staged.temp_path.to_string_lossy().to_string(),
+            ]);
+            
+            let span = match (limiter_applied, measured.is_some()) {
+                (true, _) => 90.0..100.0,
+                (false, true) => 45.0..90.0,
+                (false, false) => 0.0..90.0,
+            };
+            let stderr = jobs::run_ffmpeg_with_progress(app, &job, &ffmpeg_path, &args, output_duration, span, &file_name).await?;
+            let levels = levels::analyze_levels(&ffmpeg_path, &staged.temp_path, &job).await?;
+            
+            if levels.clipping && options.auto_limit && !limiter_applied {
+                filter_chain.push(levels::SAFETY_LIMITER.to_string());
+                limiter_applied = true;
+                continue;
+            }
+            
+            let loudness = match &measured {
+                Some(measured) => Some(loudness::report(measured, &loudness::parse_stats(&stderr)?)?),
+                None => None,
+            };
+            break Ok((loudness, levels, limiter_applied));
+        }
+    }
+    .await;
     
-    let ffprobe_path = exe_dir.join(ffprobe_name);
+    // Dropping `staged` on error removes the partial temp file
+    let (loudness, levels, limiter_applied) = match rendered {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            let status = if e.is_cancelled() { "cancelled" } else { "failed" };
+            jobs::emit_progress(app, &job_id, 0.0, status, &file_name);
+            return Err(e);
+        }
+    };
     
-    if !ffprobe_path.exists() {
-        return Err("FFprobe binary not found in application directory".to_string());
+    // The collision policy was applied when the path was claimed, so only Overwrite may replace a file
+    if let Err(e) = staged.commit(options.collision == CollisionPolicy::Overwrite) {
+        jobs::emit_progress(app, &job_id, 0.0, "failed", &file_name);
+        return Err(e);
     }
     
-    Ok(ffprobe_path)
+    jobs::emit_progress(app, &job_id, 100.0, "completed", &file_name);
+    
+    Ok(ConversionResult {
+        message: format!("Successfully processed {} with {:+.2} semitones shift", file_name, semitones),
+        job_id,
+        skipped: false,
+        output_path,
+        semitones,
+        pitch_ratio: pitch.applied_ratio,
+        tempo: options.tempo,
+        output_duration,
+        loudness,
+        levels: Some(levels),
+        limiter_applied,
+        key_shift,
+    })
 }
 
 #[tauri::command]
-async fn get_audio_info(file_path: String) -> Result<AudioFile, String> {
+async fn get_audio_info(
+    tools: tauri::State<'_, ToolLocator>,
+    file_path: String,
+    detect_key: Option<bool>,
+) -> AppResult<AudioFile> {
+    read_audio_info(&tools, file_path, detect_key.unwrap_or(false)).await
+}
+
+async fn read_audio_info(tools: &ToolLocator, file_path: String, detect_key: bool) -> AppResult<AudioFile> {
     let path = Path::new(&file_path);
     
     if !path.exists() {
-        return Err("File does not exist".to_string());
+        return Err(AppError::InputNotFound { path: file_path });
     }
     
-    let metadata = std::fs::metadata(&path).map_err(|e| e.to_string())?;
+    let metadata = std::fs::metadata(path).map_err(|e| AppError::io("Failed to read file metadata", e))?;
     let file_name = path.file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("Unknown")
         .to_string();
     
-    // Get duration using FFprobe
-    let duration = get_audio_duration(&file_path).await.ok();
+    // Get duration and stream details using FFprobe, if it is installed
+    let stream = match tools.resolve(Tool::Ffprobe) {
+        Ok(ffprobe_path) => probe::probe_audio(&ffprobe_path, &file_path).await.ok(),
+        Err(_) => None,
+    };
+    let duration = stream.as_ref().and_then(|stream| stream.duration);
     
     let format = path
         .extension()
         .and_then(|s| s.to_str())
         .map(|s| s.to_uppercase());
     
+    // Key detection decodes the whole file, so it only runs on request
+    let key = match detect_key.then(|| tools.resolve(Tool::Ffmpeg)) {
+        Some(Ok(ffmpeg_path)) => key_detection::detect_key(&ffmpeg_path, &file_path).await.ok(),
+        _ => None,
+    };
+    
     Ok(AudioFile {
         name: file_name,
         path: file_path,
         size: metadata.len(),
         duration,
         format,
+        stream,
+        key,
     })
 }
 
-async fn get_audio_duration(file_path: &str) -> Result<f64, String> {
-    let ffprobe_path = get_bundled_ffprobe_path()?;
-    
-    let output = Command::new(ffprobe_path)
-        .args([
-            "-v", "quiet",
-            "-show_entries", "format=duration",
-            "-of", "csv=p=0",
-            file_path
-        ])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to execute FFprobe: {}", e))?;
-    
-    if !output.status.success() {
-        return Err("Failed to get audio duration".to_string());
+#[tauri::command]
+async fn detect_audio_key(
+    tools: tauri::State<'_, ToolLocator>,
+    file_path: String,
+) -> AppResult<KeyEstimate> {
+    if !Path::new(&file_path).exists() {
+        return Err(AppError::InputNotFound { path: file_path });
     }
     
-    let duration_str = String::from_utf8_lossy(&output.stdout);
-    duration_str.trim().parse::<f64>()
-        .map_err(|e| format!("Failed to parse duration: {}", e))
+    let ffmpeg_path = tools.resolve(Tool::Ffmpeg)?;
+    key_detection::detect_key(&ffmpeg_path, &file_path).await
+}
+
+#[tauri::command]
+async fn get_media_info(app: AppHandle, url: String) -> AppResult<MediaInfo> {
+    download::lookup_media(&app, &url).await
+}
+
+#[tauri::command]
+async fn download_media(
+    app: AppHandle,
+    url: String,
+    output_dir: String,
+    format_id: Option<String>, // From get_media_info, None picks the best audio
+    audio_format: Option<DownloadFormat>, // None keeps the original stream
+    job_id: Option<String>,
+) -> AppResult<serde_json::Value> {
+    let job_id = job_id.unwrap_or_else(jobs::new_job_id);
+    download::download_audio(&app, job_id, url, output_dir, format_id, audio_format.unwrap_or_default()).await
 }
 
+// Kept for existing frontends, any supported site works here too
 #[tauri::command]
 async fn download_youtube_audio(
+    app: AppHandle,
     url: String,
     output_dir: String,
-) -> Result<serde_json::Value, String> {
-    if !url.contains("youtube.com") && !url.contains("youtu.be") {
-        return Err("Invalid YouTube URL".to_string());
-    }
-    
-    let ytdlp_path = get_bundled_ytdlp_path()?;
-    let output_template = format!("{}/%(title)s.%(ext)s", output_dir);
-    
-    let output = Command::new(ytdlp_path)
-        .args([
-            "-x",
-            "--audio-format", "mp3",
-            "--audio-quality", "0",
-            "--print", "after_move:filepath",
-            "-o", &output_template,
-            &url
-        ])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
-    
-    if !output.status.success() {
-        return Err(format!("yt-dlp error: {}", String::from_utf8_lossy(&output.stderr)));
-    }
-    
-    // Parse output to get downloaded file path
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let lines: Vec<&str> = stdout.trim().split('\n').collect();
-    
-    // The last line should contain the file path
-    if let Some(file_path) = lines.last() {
-        let file_path = file_path.trim();
-        if !file_path.is_empty() && std::path::Path::new(file_path).exists() {
-            // Get file info for the downloaded file
-            match get_audio_info(file_path.to_string()).await {
-                Ok(file_info) => {
-                    return Ok(serde_json::json!({
-                        "success": true,
-                        "message": format!("Successfully downloaded: {}", url),
-                        "file": file_info
-                    }));
-                }
-                Err(_) => {
-                    return Ok(serde_json::json!({
-                        "success": true,
-                        "message": format!("Successfully downloaded: {}", url),
-                        "file": null
-                    }));
-                }
-            }
-        }
-    }
-    
-    Ok(serde_json::json!({
-        "success": true,
-        "message": format!("Successfully downloaded: {}", url),
-        "file": null
-    }))
+    format_id: Option<String>,
+    audio_format: Option<DownloadFormat>,
+    job_id: Option<String>,
+) -> AppResult<serde_json::Value> {
+    download_media(app, url, output_dir, format_id, audio_format, job_id).await
 }
 
-fn get_bundled_ytdlp_path() -> Result<PathBuf, String> {
-    let mut exe_dir = std::env::current_exe()
-        .map_err(|e| format!("Failed to get executable directory: {}", e))?;
-    exe_dir.pop();
-    
-    #[cfg(target_os = "windows")]
-    let ytdlp_name = "yt-dlp.exe";
-    #[cfg(not(target_os = "windows"))]
-    let ytdlp_name = "yt-dlp";
-    
-    let ytdlp_path = exe_dir.join(ytdlp_name);
-    
-    if !ytdlp_path.exists() {
-        return Err("yt-dlp binary not found in application directory".to_string());
+#[tauri::command]
+async fn get_playlist_info(app: AppHandle, url: String) -> AppResult<PlaylistInfo> {
+    playlist::list_playlist(&app, &url).await
+}
+
+#[tauri::command]
+async fn download_playlist(
+    app: AppHandle,
+    url: String,
+    output_dir: String,
+    items: Option<String>, // e.g. "1-3,7,10-", None downloads every entry
+    audio_format: Option<DownloadFormat>,
+    max_concurrency: Option<usize>,
+    job_id: Option<String>,
+) -> AppResult<PlaylistDownloadResult> {
+    let job_id = job_id.unwrap_or_else(jobs::new_job_id);
+    let audio_format = audio_format.unwrap_or_default();
+    playlist::download_playlist(app, job_id, url, output_dir, items, audio_format, max_concurrency.unwrap_or(2)).await
+}
+
+#[tauri::command]
+async fn list_media_extractors(tools: tauri::State<'_, ToolLocator>) -> AppResult<Vec<String>> {
+    download::list_extractors(&tools.resolve(Tool::YtDlp)?).await
+}
+
+#[tauri::command]
+fn get_allowed_extractors(allow_list: tauri::State<'_, ExtractorAllowList>) -> Option<Vec<String>> {
+    allow_list.get()
+}
+
+#[tauri::command]
+async fn set_allowed_extractors(
+    tools: tauri::State<'_, ToolLocator>,
+    allow_list: tauri::State<'_, ExtractorAllowList>,
+    extractors: Option<Vec<String>>, // None allows every extractor
+) -> AppResult<Option<Vec<String>>> {
+    let known = match &extractors {
+        Some(_) => download::list_extractors(&tools.resolve(Tool::YtDlp)?).await?,
+        None => Vec::new(),
+    };
+    allow_list.set(extractors, &known)
+}
+
+#[tauri::command]
+fn cancel_job(jobs: tauri::State<'_, JobRegistry>, job_id: String) -> bool {
+    jobs.cancel(&job_id)
+}
+
+#[tauri::command]
+async fn get_tool_status(tools: tauri::State<'_, ToolLocator>) -> AppResult<Vec<ToolStatus>> {
+    let mut statuses = Vec::new();
+    for tool in Tool::ALL {
+        statuses.push(tools.status(tool).await);
     }
-    
-    Ok(ytdlp_path)
+    Ok(statuses)
+}
+
+#[tauri::command]
+async fn set_tool_path(
+    tools: tauri::State<'_, ToolLocator>,
+    tool: Tool,
+    path: Option<String>, // None clears the override
+) -> AppResult<ToolStatus> {
+    tools.set_override(tool, path.map(PathBuf::from))?;
+    Ok(tools.status(tool).await)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -251,10 +588,33 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             greet,
             process_audio_file,
+            process_audio_batch,
             get_audio_info,
-            download_youtube_audio
+            detect_audio_key,
+            get_media_info,
+            download_media,
+            download_youtube_audio,
+            get_playlist_info,
+            download_playlist,
+            list_media_extractors,
+            get_allowed_extractors,
+            set_allowed_extractors,
+            cancel_job,
+            get_tool_status,
+            set_tool_path
         ])
+        .manage(JobRegistry::default())
         .setup(|app| {
+            let resource_dir = app.path().resource_dir().ok();
+            let config_path = app.path().app_config_dir().ok().map(|dir| dir.join("tools.json"));
+            app.manage(ToolLocator::new(resource_dir, config_path));
+            app.manage(ExtractorAllowList::new(
+                app.path().app_config_dir().ok().map(|dir| dir.join("extractors.json")),
+            ));
+            
+            // Clean up temp outputs from conversions that were interrupted by a crash or forced quit
+            app.manage(staging::Journal::open(app.path().app_data_dir().ok().map(|dir| dir.join("pending-outputs"))));
+            
             // Set up file drop handling
             #[cfg(not(any(target_os = "android", target_os = "ios")))]
             {
@@ -276,7 +636,7 @@ pub fn run() {
                                     .and_then(|s| s.to_str())
                                     .unwrap_or("")
                                     .to_lowercase();
-                                ["mp3", "wav", "flac", "m4a", "aac", "ogg"].contains(&ext.as_str())
+                                ["mp3", "wav", "flac", "m4a", "aac", "ogg", "opus", "webm", "weba"].contains(&ext.as_str())
                             })
                             .collect();
                         
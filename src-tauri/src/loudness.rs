@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+// EBU R128 normalization targets for FFmpeg's loudnorm filter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoudnessOptions {
+    pub target_lufs: f64, // Integrated loudness, -70 to -5
+    pub true_peak_db: f64, // Maximum true peak in dBTP, -9 to 0
+    pub loudness_range: f64, // LRA target in LU, 1 to 50
+}
+
+impl Default for LoudnessOptions {
+    fn default() -> Self {
+        Self {
+            target_lufs: -16.0,
+            true_peak_db: -1.5,
+            loudness_range: 11.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoudnessMeasurement {
+    pub integrated_lufs: f64,
+    pub true_peak_db: f64,
+    pub loudness_range: f64,
+    pub threshold_lufs: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoudnessReport {
+    pub before: LoudnessMeasurement,
+    pub after: LoudnessMeasurement,
+    pub normalization_type: String, // "linear", or "dynamic" when the target could not be reached with a plain gain change
+}
+
+// The JSON block loudnorm prints to stderr with print_format=json. Every value is a string.
+#[derive(Debug, Deserialize)]
+pub struct LoudnormStats {
+    input_i: String,
+    input_tp: String,
+    input_lra: String,
+    input_thresh: String,
+    output_i: String,
+    output_tp: String,
+    output_lra: String,
+    output_thresh: String,
+    normalization_type: String,
+    target_offset: String,
+}
+
+impl LoudnessOptions {
+    pub fn validate(&self) -> AppResult<()> {
+        let checks = [
+            ("Target loudness", self.target_lufs, -70.0, -5.0),
+            ("True peak", self.true_peak_db, -9.0, 0.0),
+            ("Loudness range", self.loudness_range, 1.0, 50.0),
+        ];
+
+        for (name, value, min, max) in checks {
+            if !value.is_finite() || value < min || value > max {
+                return Err(AppError::invalid_option(format!("{} must be between {} and {}, got {}", name, min, max, value)));
+            }
+        }
+        Ok(())
+    }
+
+    fn targets(&self) -> String {
+        format!("I={}:TP={}:LRA={}", self.target_lufs, self.true_peak_db, self.loudness_range)
+    }
+
+    // First pass: measure only
+    pub fn measure_filter(&self) -> String {
+        format!("loudnorm={}:print_format=json", self.targets())
+    }
+
+    // Second pass: apply a linear gain computed from the first pass measurements
+    pub fn apply_filter(&self, measured: &LoudnormStats) -> String {
+        format!(
+            "loudnorm={}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true:print_format=json",
+            self.targets(),
+            measured.input_i,
+            measured.input_tp,
+            measured.input_lra,
+            measured.input_thresh,
+            measured.target_offset,
+        )
+    }
+}
+
+// loudnorm prints its JSON after the regular log output, so take the last {...} block
+pub fn parse_stats(stderr: &str) -> AppResult<LoudnormStats> {
+    let start = stderr.rfind('{');
+    let end = stderr.rfind('}');
+
+    let json = match (start, end) {
+        (Some(start), Some(end)) if start < end => &stderr[start..=end],
+        _ => return Err(AppError::parse("Failed to parse loudnorm output", "no measurement found")),
+    };
+
+    serde_json::from_str(json).map_err(|e| AppError::parse("Failed to parse loudnorm output", e))
+}
+
+fn parse_value(name: &str, value: &str) -> AppResult<f64> {
+    // Silence measures as "-inf"
+    value.trim().parse::<f64>()
+        .map_err(|e| AppError::parse(&format!("Failed to parse loudnorm {}", name), e))
+}
+
+impl LoudnormStats {
+    fn input(&self) -> AppResult<LoudnessMeasurement> {
+        Ok(LoudnessMeasurement {
+            integrated_lufs: parse_value("input_i", &self.input_i)?,
+            true_peak_db: parse_value("input_tp", &self.input_tp)?,
+            loudness_range: parse_value("input_lra", &self.input_lra)?,
+            threshold_lufs: parse_value("input_thresh", &self.input_thresh)?,
+        })
+    }
+
+    fn output(&self) -> AppResult<LoudnessMeasurement> {
+        Ok(LoudnessMeasurement {
+            integrated_lufs: parse_value("output_i", &self.output_i)?,
+            true_peak_db: parse_value("output_tp", &self.output_tp)?,
+            loudness_range: parse_value("output_lra", &self.output_lra)?,
+            threshold_lufs: parse_value("output_thresh", &self.output_thresh)?,
+        })
+    }
+}
+
+// Before comes from the measuring pass, after from the stats the second pass prints about its own output
+pub fn report(first_pass: &LoudnormStats, second_pass: &LoudnormStats) -> AppResult<LoudnessReport> {
+    Ok(LoudnessReport {
+        before: first_pass.input()?,
+        after: second_pass.output()?,
+        normalization_type: second_pass.normalization_type.to_lowercase(),
+    })
+}
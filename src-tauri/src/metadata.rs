@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::probe::AudioStreamInfo;
+
+// Muxers that can carry an attached picture stream
+const COVER_ART_FORMATS: [&str; 5] = ["mp3", "flac", "ipod", "mp4", "mov"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetadataOptions {
+    pub preserve_tags: bool,
+    pub preserve_cover_art: bool,
+    pub title_note: Option<String>, // Appended to the title, {semitones} and {key} are replaced
+    pub overrides: BTreeMap<String, String>, // Tag name to value, e.g. "album" => "Practice"
+}
+
+impl Default for MetadataOptions {
+    fn default() -> Self {
+        Self {
+            preserve_tags: true,
+            preserve_cover_art: true,
+            title_note: Some("({semitones} key)".to_string()),
+            overrides: BTreeMap::new(),
+        }
+    }
+}
+
+// "+2", "-3" for whole semitones, "+1.50" otherwise
+pub fn format_semitones(semitones: f64) -> String {
+    if (semitones - semitones.round()).abs() < 1e-6 {
+        format!("{:+}", semitones.round() as i64)
+    } else {
+        format!("{:+.2}", semitones)
+    }
+}
+
+// FFmpeg arguments that map cover art and tags from input 0 into the output
+pub fn metadata_args(
+    options: &MetadataOptions,
+    stream: &AudioStreamInfo,
+    muxer: &str,
+    fallback_title: &str,
+    semitones: f64,
+    target_key: Option<&str>,
+) -> Vec<String> {
+    let mut args: Vec<String> = vec!["-map".into(), "0:a:0".into()];
+
+    if let Some(cover) = stream.cover_art_stream.filter(|_| options.preserve_cover_art && COVER_ART_FORMATS.contains(&muxer)) {
+        args.extend([
+            "-map".into(), format!("0:{}", cover),
+            "-c:v".into(), "copy".into(),
+            "-disposition:v:0".into(), "attached_pic".into(),
+        ]);
+    }
+
+    if muxer == "mp3" {
+        // ID3v2.3 is what most players and car stereos actually read
+        args.extend(["-id3v2_version".into(), "3".into()]);
+    }
+
+    args.extend([
+        "-map_metadata".into(),
+        if options.preserve_tags { "0".into() } else { "-1".into() },
+    ]);
+
+    let mut tags = BTreeMap::new();
+
+    // Re-write the main tags explicitly, Ogg/Opus inputs store them on the stream where -map_metadata 0 misses them
+    if options.preserve_tags {
+        for (key, value) in [
+            ("artist", &stream.tags.artist),
+            ("album", &stream.tags.album),
+        ] {
+            if let Some(value) = value {
+                tags.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+
+    if !options.overrides.contains_key("title") {
+        let title = stream.tags.title.as_deref()
+            .filter(|_| options.preserve_tags)
+            .unwrap_or(fallback_title);
+        let note = options.title_note.as_deref()
+            .filter(|note| !note.is_empty() && semitones.abs() > 1e-6)
+            .map(|note| {
+                note.replace("{semitones}", &format_semitones(semitones))
+                    .replace("{key}", target_key.unwrap_or_default())
+            });
+
+        let title = match note {
+            Some(note) => format!("{} {}", title, note),
+            None => title.to_string(),
+        };
+        tags.insert("title".to_string(), title);
+    }
+
+    for (key, value) in &options.overrides {
+        tags.insert(key.clone(), value.clone());
+    }
+
+    for (key, value) in tags {
+        args.extend(["-metadata".into(), format!("{}={}", key, value)]);
+    }
+
+    args
+}
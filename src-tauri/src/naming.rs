@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::error::{AppError, AppResult};
+use crate::metadata::format_semitones;
+
+pub const DEFAULT_TEMPLATE: &str = "{stem} [{semitones:+}]{ext}";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionPolicy {
+    #[default]
+    Overwrite,
+    Skip,
+    AutoNumber, // "name (2).mp3", "name (3).mp3", ...
+    Fail,
+}
+
+// Values available to filename templates
+pub struct NameContext<'a> {
+    pub stem: &'a str,
+    pub extension: &'a str,
+    pub semitones: f64,
+    pub key: Option<&'a str>,
+    pub title: Option<&'a str>,
+}
+
+pub enum OutputTarget {
+    Write(OutputReservation),
+    Skip(PathBuf),
+}
+
+// Characters that are not allowed in file names on at least one supported platform
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+// Expand {stem}, {ext} (with leading dot), {format}, {semitones}, {semitones:+}, {key} and {title}.
+// {key} is the target key, so it is only available when converting with a "key" key_target.
+pub fn render_template(template: &str, context: &NameContext) -> AppResult<String> {
+    // The result is joined onto output_dir and must not be able to leave it
+    if template.contains(['/', '\\']) || template.contains("..") {
+        return Err(AppError::invalid_option(format!(
+            "Filename template must not contain path separators or \"..\": {}",
+            template,
+        )));
+    }
+    if template.contains("{key}") && context.key.is_none() {
+        return Err(AppError::invalid_option(
+            "The {key} placeholder needs a key_target that names the target key",
+        ));
+    }
+
+    let rounded = context.semitones.round();
+    let plain_semitones = if (context.semitones - rounded).abs() < 1e-6 {
+        format!("{}", rounded as i64)
+    } else {
+        format!("{:.2}", context.semitones)
+    };
+
+    Ok(template
+        .replace("{stem}", &sanitize(context.stem))
+        .replace("{ext}", &format!(".{}", context.extension))
+        .replace("{format}", context.extension)
+        .replace("{semitones:+}", &format_semitones(context.semitones))
+        .replace("{semitones}", &plain_semitones)
+        .replace("{key}", &sanitize(context.key.unwrap_or_default()))
+        .replace("{title}", &sanitize(context.title.unwrap_or(context.stem))))
+}
+
+// Output paths claimed by conversions that are still running, so concurrent batch items
+// never pick the same auto-numbered name before either file exists on disk
+fn reserved_outputs() -> &'static Mutex<HashSet<PathBuf>> {
+    static RESERVED: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+    RESERVED.get_or_init(Default::default)
+}
+
+pub struct OutputReservation {
+    pub path: PathBuf,
+}
+
+impl Drop for OutputReservation {
+    fn drop(&mut self) {
+        reserved_outputs().lock().unwrap().remove(&self.path);
+    }
+}
+
+// Apply the collision policy to a candidate path and reserve the result
+pub fn claim_output(path: PathBuf, policy: CollisionPolicy) -> AppResult<OutputTarget> {
+    let mut reserved = reserved_outputs().lock().unwrap();
+    let taken = |candidate: &Path| candidate.exists() || reserved.contains(candidate);
+
+    let path = if !taken(&path) {
+        path
+    } else {
+        match policy {
+            CollisionPolicy::Overwrite if !reserved.contains(&path) => path,
+            CollisionPolicy::Skip => return Ok(OutputTarget::Skip(path)),
+            CollisionPolicy::AutoNumber => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+                let extension = path.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+                (2..)
+                    .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+                    .find(|candidate| !taken(candidate))
+                    .unwrap()
+            }
+            _ => {
+                return Err(AppError::OutputExists {
+                    path: path.to_string_lossy().to_string(),
+                })
+            }
+        }
+    };
+
+    reserved.insert(path.clone());
+    Ok(OutputTarget::Write(OutputReservation { path }))
+}
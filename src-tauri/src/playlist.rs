@@ -0,0 +1,292 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use tauri::{AppHandle, Manager};
+use tokio::process::Command;
+
+use crate::batch::{self, BatchSummary, QueueItem};
+use crate::download::{self, DownloadFormat, ExtractorAllowList};
+use crate::error::{AppError, AppResult};
+use crate::jobs::JobStatus;
+use crate::tools::{Tool, ToolLocator};
+use crate::AudioFile;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    pub index: usize, // 1-based position, as used by item selections
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub duration: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistInfo {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub uploader: Option<String>,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlaylistItemResult {
+    pub index: usize,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub status: JobStatus,
+    pub file: Option<AudioFile>,
+    pub error: Option<AppError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlaylistDownloadResult {
+    pub job_id: String,
+    pub playlist: PlaylistInfo,
+    pub items: Vec<PlaylistItemResult>,
+    pub summary: BatchSummary,
+}
+
+// The subset of `yt-dlp --flat-playlist -J` we use. A single video comes back without entries.
+#[derive(Deserialize)]
+struct FlatInfo {
+    id: Option<String>,
+    title: Option<String>,
+    uploader: Option<String>,
+    channel: Option<String>,
+    webpage_url: Option<String>,
+    duration: Option<f64>,
+    entries: Option<Vec<FlatEntry>>,
+}
+
+#[derive(Deserialize)]
+struct FlatEntry {
+    #[serde(rename = "_type")]
+    kind: Option<String>,
+    ie_key: Option<String>,
+    id: Option<String>,
+    title: Option<String>,
+    url: Option<String>,
+    webpage_url: Option<String>,
+    duration: Option<f64>,
+}
+
+impl FlatEntry {
+    // Flat listings report nested playlists, like the tabs of a channel page or the albums of a
+    // Bandcamp artist, as URL entries for a playlist, tab, album or set extractor. Entries this misses
+    // are still single items to `download::download_file`, which refuses to download a playlist.
+    fn is_playlist(&self) -> bool {
+        self.kind.as_deref() == Some("playlist")
+            || self.ie_key.as_deref().is_some_and(|key| {
+                key.contains("Playlist") || ["Tab", "Album", "Set"].iter().any(|suffix| key.ends_with(suffix))
+            })
+    }
+
+    fn url(&self) -> Option<&str> {
+        self.webpage_url.as_deref().or(self.url.as_deref())
+    }
+}
+
+async fn flat_listing(app: &AppHandle, url: &str) -> AppResult<FlatInfo> {
+    let ytdlp_path = app.state::<ToolLocator>().resolve(Tool::YtDlp)?;
+
+    let output = Command::new(ytdlp_path)
+        .args(app.state::<ExtractorAllowList>().args())
+        .args(["--flat-playlist", "-J", url])
+        .output()
+        .await
+        .map_err(|e| AppError::io("Failed to execute yt-dlp", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("Unsupported URL") {
+            return Err(AppError::InvalidUrl { url: url.to_string() });
+        }
+        return Err(AppError::tool_failed("yt-dlp", output.status, &stderr));
+    }
+
+    serde_json::from_slice(&output.stdout).map_err(|e| AppError::parse("Failed to parse yt-dlp playlist", e))
+}
+
+// List the entries of a playlist without downloading anything. A channel page lists its tabs
+// (Videos, Shorts, Live) as entries, so those are listed in turn and their entries flattened.
+pub async fn list_playlist(app: &AppHandle, url: &str) -> AppResult<PlaylistInfo> {
+    let parsed = download::parse_media_url(url)?;
+    let info = flat_listing(app, parsed.as_str()).await?;
+
+    let Some(flat_entries) = info.entries else {
+        return Ok(PlaylistInfo {
+            id: info.id.clone(),
+            title: info.title.clone(),
+            uploader: info.uploader.or(info.channel),
+            entries: vec![PlaylistEntry {
+                index: 1,
+                id: info.id,
+                title: info.title,
+                url: info.webpage_url.or_else(|| Some(parsed.to_string())),
+                duration: info.duration,
+            }],
+        });
+    };
+
+    let mut videos = Vec::new();
+    for entry in flat_entries {
+        if !entry.is_playlist() {
+            videos.push(entry);
+            continue;
+        }
+
+        let tab_url = entry
+            .url()
+            .ok_or_else(|| AppError::unsupported("yt-dlp did not report a URL for a nested playlist"))?;
+        let tab = flat_listing(app, tab_url).await?;
+
+        for tab_entry in tab.entries.unwrap_or_default() {
+            if tab_entry.is_playlist() {
+                return Err(AppError::unsupported(format!(
+                    "{} contains playlists nested more than one level deep, open one of its playlists instead",
+                    url,
+                )));
+            }
+            videos.push(tab_entry);
+        }
+    }
+
+    // The same video can show up under more than one tab
+    let mut seen = HashSet::new();
+    videos.retain(|entry| entry.id.as_ref().is_none_or(|id| seen.insert(id.clone())));
+
+    let entries = videos
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| PlaylistEntry {
+            index: index + 1,
+            url: entry.url().map(str::to_string),
+            id: entry.id,
+            title: entry.title,
+            duration: entry.duration,
+        })
+        .collect();
+
+    Ok(PlaylistInfo {
+        id: info.id,
+        title: info.title,
+        uploader: info.uploader.or(info.channel),
+        entries,
+    })
+}
+
+// Parse an item selection like "1-3,7,10-" into sorted 1-based indices. None selects everything.
+pub fn parse_selection(selection: Option<&str>, count: usize) -> AppResult<Vec<usize>> {
+    let Some(selection) = selection.map(str::trim).filter(|selection| !selection.is_empty()) else {
+        return Ok((1..=count).collect());
+    };
+
+    let invalid = |part: &str| AppError::invalid_option(format!("Invalid playlist item selection: {}", part));
+    let parse_index = |value: &str, part: &str| -> AppResult<usize> {
+        value.trim().parse::<usize>().ok().filter(|index| *index >= 1).ok_or_else(|| invalid(part))
+    };
+
+    let mut indices = BTreeSet::new();
+    for part in selection.split(',') {
+        let (first, last) = match part.split_once('-') {
+            Some((first, last)) => {
+                let first = if first.trim().is_empty() { 1 } else { parse_index(first, part)? };
+                let last = if last.trim().is_empty() { count } else { parse_index(last, part)? };
+                (first, last)
+            }
+            None => {
+                let index = parse_index(part, part)?;
+                (index, index)
+            }
+        };
+
+        if first > last || last > count {
+            return Err(AppError::invalid_option(format!(
+                "Playlist item selection {} is outside the playlist (1-{})",
+                part.trim(),
+                count,
+            )));
+        }
+        indices.extend(first..=last);
+    }
+
+    Ok(indices.into_iter().collect())
+}
+
+// Items download side by side into one directory, so the video id keeps equal titles apart
+const ITEM_FILE_TEMPLATE: &str = "%(title)s [%(id)s].%(ext)s";
+
+// Download the selected entries as a batch job. Items run as "<job id>/<index>" so they can
+// be cancelled together, and each item reports its own file or error.
+pub async fn download_playlist(
+    app: AppHandle,
+    job_id: String,
+    url: String,
+    output_dir: String,
+    selection: Option<String>,
+    audio_format: DownloadFormat,
+    max_concurrency: usize,
+) -> AppResult<PlaylistDownloadResult> {
+    let playlist = list_playlist(&app, &url).await?;
+    let selected = parse_selection(selection.as_deref(), playlist.entries.len())?;
+
+    if selected.is_empty() {
+        return Err(AppError::invalid_option("The playlist has no entries to download"));
+    }
+
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| AppError::io("Failed to create output directory", e))?;
+
+    let queue = selected
+        .iter()
+        .map(|index| {
+            let entry = &playlist.entries[index - 1];
+            let app = app.clone();
+            let item_job_id = format!("{}/{}", job_id, entry.index);
+            let entry_url = entry.url.clone();
+            let output_dir = output_dir.clone();
+
+            QueueItem {
+                job_id: item_job_id.clone(),
+                label: entry.title.clone().unwrap_or_default(),
+                work: async move {
+                    match entry_url {
+                        Some(entry_url) => download::download_file(&app, &item_job_id, &entry_url, &output_dir, ITEM_FILE_TEMPLATE, None, audio_format).await,
+                        None => Err(AppError::unsupported("yt-dlp did not report a URL for this entry")),
+                    }
+                },
+            }
+        })
+        .collect();
+
+    let outcomes = batch::run_queue(&app, &job_id, max_concurrency.clamp(1, 8), queue).await?;
+
+    let items: Vec<PlaylistItemResult> = selected
+        .iter()
+        .zip(outcomes)
+        .map(|(index, outcome)| {
+            let entry = &playlist.entries[index - 1];
+            let (status, file, error) = match outcome {
+                Ok(file) => (JobStatus::Completed, file, None),
+                Err(e) => (if e.is_cancelled() { JobStatus::Cancelled } else { JobStatus::Failed }, None, Some(e)),
+            };
+
+            PlaylistItemResult {
+                index: entry.index,
+                title: entry.title.clone(),
+                url: entry.url.clone(),
+                status,
+                file,
+                error,
+            }
+        })
+        .collect();
+
+    let summary = BatchSummary::tally(items.iter().map(|item| item.status));
+
+    Ok(PlaylistDownloadResult {
+        job_id,
+        playlist,
+        items,
+        summary,
+    })
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tokio::process::Command;
+
+use crate::error::{AppError, AppResult};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioStreamInfo {
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub duration: Option<f64>,
+    pub codec: Option<String>, // e.g. "mp3", "flac", "opus"
+    pub container: Option<String>, // FFmpeg demuxer name, e.g. "mov,mp4,m4a,3gp,3g2,mj2"
+    pub channel_layout: Option<String>,
+    pub bit_depth: Option<u32>, // Only known for PCM and lossless codecs
+    pub bit_rate: Option<u64>, // Bits per second
+    #[serde(default)]
+    pub tags: AudioTags,
+    #[serde(default)]
+    pub has_cover_art: bool,
+    #[serde(default)]
+    pub cover_art_stream: Option<u32>, // Input stream index of the embedded picture
+}
+
+#[derive(Debug, Deserialize)]
+struct FfprobeOutput {
+    #[serde(default)]
+    streams: Vec<FfprobeStream>,
+    format: Option<FfprobeFormat>,
+}
+
+// ffprobe reports most numbers as strings in its JSON output
+#[derive(Debug, Deserialize)]
+struct FfprobeStream {
+    index: Option<u32>,
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    channel_layout: Option<String>,
+    bits_per_sample: Option<u32>,
+    bits_per_raw_sample: Option<String>,
+    bit_rate: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    disposition: HashMap<String, i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FfprobeFormat {
+    format_name: Option<String>,
+    duration: Option<String>,
+    bit_rate: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+// Tag keys differ in case between containers ("title" in MP3, "TITLE" in FLAC/Vorbis)
+fn find_tag(tags: &HashMap<String, String>, key: &str) -> Option<String> {
+    tags.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+pub async fn probe_audio(ffprobe_path: &Path, file_path: &str) -> AppResult<AudioStreamInfo> {
+    let output = Command::new(ffprobe_path)
+        .args([
+            "-v", "quiet",
+            "-print_format", "json",
+            "-show_format",
+            "-show_streams",
+            file_path,
+        ])
+        .output()
+        .await
+        .map_err(|e| AppError::io("Failed to execute FFprobe", e))?;
+
+    if !output.status.success() {
+        return Err(AppError::tool_failed("ffprobe", output.status, &String::from_utf8_lossy(&output.stderr)));
+    }
+
+    let probe: FfprobeOutput = serde_json::from_slice(&output.stdout)
+        .map_err(|e| AppError::parse("Failed to parse FFprobe output", e))?;
+
+    let stream = probe.streams.iter()
+        .find(|stream| stream.codec_type.as_deref() == Some("audio"))
+        .ok_or_else(|| AppError::unsupported("No audio stream found in file"))?;
+
+    let sample_rate = stream.sample_rate.as_deref()
+        .ok_or_else(|| AppError::parse("Failed to parse sample rate", "missing from FFprobe output"))?
+        .parse::<u32>()
+        .map_err(|e| AppError::parse("Failed to parse sample rate", e))?;
+
+    let bit_depth = stream.bits_per_raw_sample.as_deref()
+        .and_then(|bits| bits.parse::<u32>().ok())
+        .or(stream.bits_per_sample)
+        .filter(|bits| *bits > 0);
+
+    let format = probe.format.as_ref();
+    let bit_rate = stream.bit_rate.as_deref()
+        .or_else(|| format.and_then(|format| format.bit_rate.as_deref()))
+        .and_then(|rate| rate.parse::<u64>().ok());
+
+    // Embedded album art shows up as a video stream flagged as an attached picture. A video file
+    // can carry both a real video track and a thumbnail, so the picture's own index is kept.
+    let cover_art_stream = probe.streams.iter()
+        .find(|stream| {
+            stream.codec_type.as_deref() == Some("video")
+                && stream.disposition.get("attached_pic") == Some(&1)
+        })
+        .and_then(|stream| stream.index);
+
+    // Ogg and Opus keep their tags on the stream rather than the container
+    let lookup = |key: &str| {
+        format.and_then(|format| find_tag(&format.tags, key))
+            .or_else(|| find_tag(&stream.tags, key))
+    };
+
+    Ok(AudioStreamInfo {
+        sample_rate,
+        channels: stream.channels.unwrap_or(2),
+        duration: format
+            .and_then(|format| format.duration.as_deref())
+            .and_then(|duration| duration.trim().parse::<f64>().ok()),
+        codec: stream.codec_name.clone(),
+        container: format.and_then(|format| format.format_name.clone()),
+        channel_layout: stream.channel_layout.clone(),
+        bit_depth,
+        bit_rate,
+        tags: AudioTags {
+            title: lookup("title"),
+            artist: lookup("artist"),
+            album: lookup("album"),
+        },
+        has_cover_art: cover_art_stream.is_some(),
+        cover_art_stream,
+    })
+}
@@ -0,0 +1,148 @@
+use tokio::process::{Child, Command};
+
+// Killing a tool must also stop the processes it started, e.g. the FFmpeg that yt-dlp runs for
+// audio extraction. On Unix the tool leads its own process group, on Windows it runs in a job object.
+pub fn isolate(command: &mut Command) {
+    #[cfg(unix)]
+    command.process_group(0);
+    #[cfg(not(unix))]
+    let _ = command;
+}
+
+pub struct ProcessTree {
+    #[cfg(unix)]
+    group: Option<i32>,
+    #[cfg(windows)]
+    job: Option<isize>, // HANDLE kept as an integer so the tree can cross await points
+}
+
+impl ProcessTree {
+    pub fn attach(child: &Child) -> Self {
+        #[cfg(unix)]
+        {
+            Self {
+                group: child.id().and_then(|pid| i32::try_from(pid).ok()),
+            }
+        }
+        #[cfg(windows)]
+        {
+            Self {
+                job: child.raw_handle().and_then(windows::create_job),
+            }
+        }
+        #[cfg(not(any(unix, windows)))]
+        {
+            let _ = child;
+            Self {}
+        }
+    }
+
+    // Only called while the tool is still running, so the group or job cannot have been reused
+    pub fn kill(&self) {
+        #[cfg(unix)]
+        if let Some(group) = self.group {
+            unsafe {
+                libc::killpg(group, libc::SIGKILL);
+            }
+        }
+        #[cfg(windows)]
+        if let Some(job) = self.job {
+            windows::terminate_job(job);
+        }
+    }
+}
+
+// Whether a process with this id still exists. Where that cannot be checked it is assumed to.
+pub fn is_running(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        let Ok(pid) = i32::try_from(pid) else {
+            return false;
+        };
+        // Signal 0 only checks for existence, EPERM means it exists but belongs to another user
+        let signalled = unsafe { libc::kill(pid, 0) } == 0;
+        signalled || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+    #[cfg(windows)]
+    {
+        windows::is_running(pid)
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = pid;
+        true
+    }
+}
+
+#[cfg(windows)]
+impl Drop for ProcessTree {
+    fn drop(&mut self) {
+        if let Some(job) = self.job {
+            windows::close_job(job);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::os::windows::io::RawHandle;
+    use windows_sys::Win32::Foundation::{CloseHandle, GetLastError, ERROR_ACCESS_DENIED, HANDLE, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation, SetInformationJobObject,
+        TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    pub fn create_job(process: RawHandle) -> Option<isize> {
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job.is_null() {
+                return None;
+            }
+
+            // Closing the last handle to the job also ends whatever the tool left behind
+            let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            let configured = SetInformationJobObject(
+                job,
+                JobObjectExtendedLimitInformation,
+                &limits as *const _ as *const _,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            );
+
+            if configured == 0 || AssignProcessToJobObject(job, process as HANDLE) == 0 {
+                CloseHandle(job);
+                return None;
+            }
+
+            Some(job as isize)
+        }
+    }
+
+    pub fn terminate_job(job: isize) {
+        unsafe {
+            TerminateJobObject(job as HANDLE, 1);
+        }
+    }
+
+    pub fn close_job(job: isize) {
+        unsafe {
+            CloseHandle(job as HANDLE);
+        }
+    }
+
+    pub fn is_running(pid: u32) -> bool {
+        unsafe {
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if process.is_null() {
+                // Like EPERM on Unix, the process exists but belongs to someone else
+                return GetLastError() == ERROR_ACCESS_DENIED;
+            }
+
+            let mut exit_code = 0;
+            let queried = GetExitCodeProcess(process, &mut exit_code);
+            CloseHandle(process);
+            queried != 0 && exit_code == STILL_ACTIVE as u32
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::error::{AppError, AppResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Mp3,
+    Wav,
+    Flac,
+    M4a,
+    Ogg,
+    Opus,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Mp3 => "mp3",
+            OutputFormat::Wav => "wav",
+            OutputFormat::Flac => "flac",
+            OutputFormat::M4a => "m4a",
+            OutputFormat::Ogg => "ogg",
+            OutputFormat::Opus => "opus",
+        }
+    }
+
+    // Name passed to FFmpeg's -f
+    pub fn muxer(self) -> &'static str {
+        match self {
+            OutputFormat::M4a => "ipod",
+            other => other.extension(),
+        }
+    }
+
+    fn encoders(self) -> &'static [&'static str] {
+        match self {
+            OutputFormat::Mp3 => &["libmp3lame", "libshine"],
+            OutputFormat::Wav => &["pcm_s16le", "pcm_s24le", "pcm_s32le", "pcm_f32le"],
+            OutputFormat::Flac => &["flac"],
+            OutputFormat::M4a => &["aac", "libfdk_aac", "aac_at"],
+            OutputFormat::Ogg => &["libvorbis", "libopus"],
+            OutputFormat::Opus => &["libopus"],
+        }
+    }
+}
+
+// Output container, encoder and quality settings. Unset values fall back to per-format defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "OutputProfileInput")]
+pub struct OutputProfile {
+    pub format: OutputFormat,
+    pub codec: Option<String>, // Encoder name, e.g. "libfdk_aac"
+    pub bitrate_kbps: Option<u32>, // Constant/average bitrate for lossy formats
+    pub vbr_quality: Option<f32>, // MP3 0-9 (lower is better), Vorbis 0-10, FDK AAC 1-5
+    pub compression_level: Option<u8>, // FLAC 0-12
+    pub bit_depth: Option<u8>, // WAV 16/24/32, FLAC 16/24
+}
+
+// Accept a bare format string, as older callers send, or a full profile object
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OutputProfileInput {
+    Format(OutputFormat),
+    Profile {
+        format: OutputFormat,
+        #[serde(default)]
+        codec: Option<String>,
+        #[serde(default)]
+        bitrate_kbps: Option<u32>,
+        #[serde(default)]
+        vbr_quality: Option<f32>,
+        #[serde(default)]
+        compression_level: Option<u8>,
+        #[serde(default)]
+        bit_depth: Option<u8>,
+    },
+}
+
+impl From<OutputProfileInput> for OutputProfile {
+    fn from(input: OutputProfileInput) -> Self {
+        match input {
+            OutputProfileInput::Format(format) => OutputProfile::new(format),
+            OutputProfileInput::Profile { format, codec, bitrate_kbps, vbr_quality, compression_level, bit_depth } => {
+                OutputProfile { format, codec, bitrate_kbps, vbr_quality, compression_level, bit_depth }
+            }
+        }
+    }
+}
+
+impl OutputProfile {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            codec: None,
+            bitrate_kbps: None,
+            vbr_quality: None,
+            compression_level: None,
+            bit_depth: None,
+        }
+    }
+
+    pub fn encoder(&self) -> String {
+        if let Some(codec) = &self.codec {
+            return codec.clone();
+        }
+
+        match (self.format, self.bit_depth) {
+            (OutputFormat::Wav, Some(24)) => "pcm_s24le".to_string(),
+            (OutputFormat::Wav, Some(32)) => "pcm_f32le".to_string(),
+            (format, _) => format.encoders()[0].to_string(),
+        }
+    }
+
+    // Opus only encodes at a handful of rates; everything else takes what it is given
+    pub fn output_sample_rate(&self, requested: u32) -> u32 {
+        let encoder = self.encoder();
+        if encoder == "libopus" && ![8000, 12000, 16000, 24000, 48000].contains(&requested) {
+            return 48000;
+        }
+        requested
+    }
+
+    // Check the settings make sense for the format and that this FFmpeg build has the encoder
+    pub fn validate(&self, available_encoders: &HashSet<String>) -> AppResult<()> {
+        let encoder = self.encoder();
+        let format = self.format.extension();
+
+        if !self.format.encoders().contains(&encoder.as_str()) {
+            return Err(AppError::invalid_option(format!("Encoder {} cannot be used for {} output", encoder, format)));
+        }
+        if !available_encoders.contains(&encoder) {
+            return Err(AppError::unsupported(format!("The bundled FFmpeg was built without the {} encoder", encoder)));
+        }
+
+        let lossless = matches!(self.format, OutputFormat::Wav | OutputFormat::Flac);
+
+        if let Some(kbps) = self.bitrate_kbps {
+            let range = if encoder == "libopus" { 6..=510 } else { 32..=320 };
+            if lossless || !range.contains(&kbps) {
+                return Err(AppError::invalid_option(format!("Bitrate of {} kbps is not valid for {} output", kbps, format)));
+            }
+        }
+
+        if let Some(quality) = self.vbr_quality {
+            let range = match encoder.as_str() {
+                "libmp3lame" => 0.0..=9.0,
+                "libvorbis" => 0.0..=10.0,
+                "libfdk_aac" => 1.0..=5.0,
+                _ => return Err(AppError::invalid_option(format!("VBR quality is not supported by the {} encoder", encoder))),
+            };
+            if !range.contains(&quality) {
+                return Err(AppError::invalid_option(format!("VBR quality {} is out of range for {}", quality, encoder)));
+            }
+        }
+
+        if let Some(level) = self.compression_level {
+            if self.format != OutputFormat::Flac || level > 12 {
+                return Err(AppError::invalid_option(format!("Compression level {} is not valid for {} output", level, format)));
+            }
+        }
+
+        if let Some(bits) = self.bit_depth {
+            let valid = match self.format {
+                OutputFormat::Wav => [16, 24, 32].contains(&bits),
+                OutputFormat::Flac => [16, 24].contains(&bits),
+                _ => false,
+            };
+            if !valid {
+                return Err(AppError::invalid_option(format!("Bit depth {} is not valid for {} output", bits, format)));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Encoder and quality arguments, placed before the output file
+    pub fn codec_args(&self) -> Vec<String> {
+        let encoder = self.encoder();
+        let mut args = vec!["-c:a".to_string(), encoder.clone()];
+
+        match (self.format, self.bitrate_kbps, self.vbr_quality) {
+            (OutputFormat::Wav | OutputFormat::Flac, _, _) => {}
+            (_, Some(kbps), _) => args.extend(["-b:a".into(), format!("{}k", kbps)]),
+            (_, None, Some(quality)) if encoder == "libfdk_aac" => {
+                args.extend(["-vbr".into(), format!("{}", quality.round() as u8)]);
+            }
+            (_, None, Some(quality)) => args.extend(["-q:a".into(), format!("{}", quality)]),
+            // Defaults: LAME V2, Vorbis q6, 256k AAC, 160k Opus
+            (OutputFormat::Mp3, None, None) if encoder == "libmp3lame" => args.extend(["-q:a".into(), "2".into()]),
+            (OutputFormat::Ogg, None, None) if encoder == "libvorbis" => args.extend(["-q:a".into(), "6".into()]),
+            (OutputFormat::M4a, None, None) => args.extend(["-b:a".into(), "256k".into()]),
+            (_, None, None) if encoder == "libopus" => args.extend(["-b:a".into(), "160k".into()]),
+            _ => {}
+        }
+
+        if self.format == OutputFormat::Flac {
+            args.extend(["-compression_level".into(), self.compression_level.unwrap_or(5).to_string()]);
+
+            // 24-bit FLAC is stored in 32-bit samples with the real depth flagged
+            match self.bit_depth {
+                Some(24) => args.extend([
+                    "-sample_fmt".into(), "s32".into(),
+                    "-bits_per_raw_sample".into(), "24".into(),
+                ]),
+                Some(16) => args.extend(["-sample_fmt".into(), "s16".into()]),
+                _ => {}
+            }
+        }
+
+        args
+    }
+}
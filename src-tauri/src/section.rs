@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+const MAX_LOOP_COUNT: u32 = 100;
+
+// A position in the input, either seconds or a "1:23.5" / "01:02:03" timestamp
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TimeValue {
+    Seconds(f64),
+    Timestamp(String),
+}
+
+impl TimeValue {
+    pub fn seconds(&self) -> AppResult<f64> {
+        let seconds = match self {
+            TimeValue::Seconds(seconds) => *seconds,
+            TimeValue::Timestamp(timestamp) => parse_timestamp(timestamp)?,
+        };
+
+        if !seconds.is_finite() || seconds < 0.0 {
+            return Err(AppError::invalid_option(format!("Invalid time: {:?}", self)));
+        }
+        Ok(seconds)
+    }
+}
+
+fn parse_timestamp(timestamp: &str) -> AppResult<f64> {
+    let invalid = || AppError::invalid_option(format!("Invalid timestamp: {}", timestamp));
+
+    let parts: Vec<&str> = timestamp.trim().split(':').collect();
+    if parts.len() > 3 {
+        return Err(invalid());
+    }
+
+    let mut seconds = 0.0;
+    for (index, part) in parts.iter().enumerate() {
+        let value = part.trim().parse::<f64>().map_err(|_| invalid())?;
+        // Only the leading field may exceed its unit, e.g. "90:00" is fine but "1:75" is not
+        if value < 0.0 || (index > 0 && value >= 60.0) {
+            return Err(invalid());
+        }
+        seconds = seconds * 60.0 + value;
+    }
+
+    Ok(seconds)
+}
+
+// Export only part of the input, optionally faded and repeated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SectionOptions {
+    pub start: Option<TimeValue>, // Input time, before any pitch or tempo change
+    pub end: Option<TimeValue>,
+    pub fade_in: f64, // Seconds of output time
+    pub fade_out: f64,
+    pub loop_count: u32, // Number of times the section is played, 1 means no repeat
+}
+
+impl Default for SectionOptions {
+    fn default() -> Self {
+        Self {
+            start: None,
+            end: None,
+            fade_in: 0.0,
+            fade_out: 0.0,
+            loop_count: 1,
+        }
+    }
+}
+
+impl SectionOptions {
+    // Start and end in input seconds, checked against the input duration when it is known
+    pub fn input_range(&self, input_duration: Option<f64>) -> AppResult<(f64, Option<f64>)> {
+        let start = self.start.as_ref().map(TimeValue::seconds).transpose()?.unwrap_or(0.0);
+        let end = self.end.as_ref().map(TimeValue::seconds).transpose()?;
+
+        if let Some(end) = end {
+            if end <= start {
+                return Err(AppError::invalid_option(format!("Section end ({}s) must be after its start ({}s)", end, start)));
+            }
+        }
+        if let Some(duration) = input_duration {
+            if start >= duration {
+                return Err(AppError::invalid_option(format!("Section start ({}s) is past the end of the file ({:.2}s)", start, duration)));
+            }
+        }
+
+        // An end past the input is the same as no end
+        let end = end.filter(|end| input_duration.is_none_or(|duration| *end < duration));
+        Ok((start, end))
+    }
+
+    // Fades and repeats, applied after the pitch and tempo filters so they line up with the output
+    pub fn output_filters(&self, section_length: Option<f64>, output_rate: u32) -> AppResult<Vec<String>> {
+        for (name, seconds) in [("Fade-in", self.fade_in), ("Fade-out", self.fade_out)] {
+            if !seconds.is_finite() || seconds < 0.0 {
+                return Err(AppError::invalid_option(format!("{} length must be zero or more seconds, got {}", name, seconds)));
+            }
+        }
+        if !(1..=MAX_LOOP_COUNT).contains(&self.loop_count) {
+            return Err(AppError::invalid_option(format!("Loop count must be between 1 and {}, got {}", MAX_LOOP_COUNT, self.loop_count)));
+        }
+
+        let needs_length = self.fade_out > 0.0 || self.loop_count > 1;
+        let length = match section_length {
+            Some(length) => length,
+            None if needs_length => {
+                return Err(AppError::unsupported("Fade-out and looping need a known duration, but FFprobe could not read one"));
+            }
+            None => 0.0,
+        };
+
+        if section_length.is_some() && self.fade_in + self.fade_out > length {
+            return Err(AppError::invalid_option(format!("Fades ({}s + {}s) are longer than the section ({:.2}s)", self.fade_in, self.fade_out, length)));
+        }
+
+        let mut filters = Vec::new();
+
+        if self.fade_in > 0.0 {
+            filters.push(format!("afade=t=in:st=0:d={}", self.fade_in));
+        }
+        if self.fade_out > 0.0 {
+            filters.push(format!("afade=t=out:st={:.6}:d={}", length - self.fade_out, self.fade_out));
+        }
+        if self.loop_count > 1 {
+            let samples = (length * output_rate as f64).round() as u64;
+            filters.push(format!("aloop=loop={}:size={}", self.loop_count - 1, samples));
+        }
+
+        Ok(filters)
+    }
+}
+
+// Cut the input to [start, end) and restart timestamps at zero so later filters see a fresh stream
+pub fn trim_filters(start: f64, end: Option<f64>) -> Vec<String> {
+    let trim = match end {
+        Some(end) => format!("atrim=start={}:end={}", start, end),
+        None if start > 0.0 => format!("atrim=start={}", start),
+        None => return Vec::new(),
+    };
+
+    vec![trim, "asetpts=PTS-STARTPTS".to_string()]
+}
@@ -0,0 +1,134 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{AppError, AppResult};
+use crate::process_tree;
+
+// Temp files are hidden siblings of the final output so the rename never crosses filesystems
+const TEMP_SUFFIX: &str = ".partial";
+
+// Temp files that exist right now, persisted so a crash or forced quit can be cleaned up on the
+// next start. Each process keeps its own "<pid>.json" so a second instance can tell a crashed run's
+// files from the in-flight files of one that is still running.
+#[derive(Clone, Default)]
+pub struct Journal {
+    path: Option<PathBuf>,
+    entries: Arc<Mutex<BTreeSet<PathBuf>>>,
+}
+
+impl Journal {
+    // Remove temp files left behind by processes that are gone and start journaling in `journal_dir`
+    pub fn open(journal_dir: Option<PathBuf>) -> Self {
+        let own_pid = std::process::id();
+
+        if let Some(entries) = journal_dir.as_ref().and_then(|dir| std::fs::read_dir(dir).ok()) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let Some(pid) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse::<u32>().ok())
+                else {
+                    continue;
+                };
+
+                // A journal with our own pid belongs to an earlier process that had the same id
+                if pid != own_pid && process_tree::is_running(pid) {
+                    continue;
+                }
+
+                let stale: BTreeSet<PathBuf> = std::fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default();
+
+                for temp_path in stale {
+                    let _ = std::fs::remove_file(temp_path);
+                }
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+
+        let journal = Self {
+            path: journal_dir.map(|dir| dir.join(format!("{}.json", own_pid))),
+            entries: Default::default(),
+        };
+        journal.save(&journal.entries.lock().unwrap());
+        journal
+    }
+
+    fn save(&self, entries: &BTreeSet<PathBuf>) {
+        let Some(path) = &self.path else { return };
+
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string_pretty(entries) {
+            let _ = std::fs::write(path, json);
+        }
+    }
+
+    fn insert(&self, temp_path: &Path) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(temp_path.to_path_buf());
+        self.save(&entries);
+    }
+
+    fn remove(&self, temp_path: &Path) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(temp_path);
+        self.save(&entries);
+    }
+}
+
+fn temp_path_for(target: &Path) -> PathBuf {
+    let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+    target.with_file_name(format!(".{}{}", file_name, TEMP_SUFFIX))
+}
+
+// An output being written to a temp file, removed on drop unless committed
+pub struct StagedOutput {
+    journal: Journal,
+    pub temp_path: PathBuf,
+    target: PathBuf,
+    committed: bool,
+}
+
+impl StagedOutput {
+    pub fn new(journal: &Journal, target: &Path) -> Self {
+        let temp_path = temp_path_for(target);
+        journal.insert(&temp_path);
+
+        Self {
+            journal: journal.clone(),
+            temp_path,
+            target: target.to_path_buf(),
+            committed: false,
+        }
+    }
+
+    // Move the finished file into place. Unless `overwrite` is set an existing target is left alone.
+    pub fn commit(mut self, overwrite: bool) -> AppResult<()> {
+        if !overwrite && self.target.exists() {
+            return Err(AppError::OutputExists {
+                path: self.target.to_string_lossy().to_string(),
+            });
+        }
+
+        std::fs::rename(&self.temp_path, &self.target)
+            .map_err(|e| AppError::io("Failed to move converted file into place", e))?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for StagedOutput {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = std::fs::remove_file(&self.temp_path);
+        }
+
+        self.journal.remove(&self.temp_path);
+    }
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::process::Command;
+
+use crate::error::{AppError, AppResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Tool {
+    #[serde(rename = "ffmpeg")]
+    Ffmpeg,
+    #[serde(rename = "ffprobe")]
+    Ffprobe,
+    #[serde(rename = "yt-dlp")]
+    YtDlp,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 3] = [Tool::Ffmpeg, Tool::Ffprobe, Tool::YtDlp];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Ffmpeg => "ffmpeg",
+            Tool::Ffprobe => "ffprobe",
+            Tool::YtDlp => "yt-dlp",
+        }
+    }
+
+    fn binary_name(self) -> String {
+        #[cfg(target_os = "windows")]
+        return format!("{}.exe", self.name());
+        #[cfg(not(target_os = "windows"))]
+        return self.name().to_string();
+    }
+
+    fn version_args(self) -> &'static [&'static str] {
+        match self {
+            Tool::Ffmpeg | Tool::Ffprobe => &["-version"],
+            Tool::YtDlp => &["--version"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolSource {
+    Override,
+    Resources,
+    ExecutableDir,
+    Path,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolStatus {
+    pub tool: Tool,
+    pub path: Option<String>,
+    pub source: Option<ToolSource>,
+    pub version: Option<String>,
+    pub error: Option<AppError>,
+}
+
+// Finds ffmpeg, ffprobe and yt-dlp. Lookup order: user override, bundled resources,
+// next to the executable, then PATH. Results are cached until an override changes.
+#[derive(Default)]
+pub struct ToolLocator {
+    resource_dir: Option<PathBuf>,
+    config_path: Option<PathBuf>, // Where overrides are persisted
+    overrides: Mutex<HashMap<Tool, PathBuf>>,
+    cache: Mutex<HashMap<Tool, (PathBuf, ToolSource)>>,
+}
+
+impl ToolLocator {
+    pub fn new(resource_dir: Option<PathBuf>, config_path: Option<PathBuf>) -> Self {
+        let overrides = config_path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        Self {
+            resource_dir,
+            config_path,
+            overrides: Mutex::new(overrides),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn resolve(&self, tool: Tool) -> AppResult<PathBuf> {
+        self.locate(tool).map(|(path, _)| path)
+    }
+
+    fn locate(&self, tool: Tool) -> AppResult<(PathBuf, ToolSource)> {
+        if let Some((path, source)) = self.cache.lock().unwrap().get(&tool) {
+            if path.is_file() {
+                return Ok((path.clone(), *source));
+            }
+        }
+
+        let found = self.search(tool)?;
+        self.cache.lock().unwrap().insert(tool, found.clone());
+        Ok(found)
+    }
+
+    fn search(&self, tool: Tool) -> AppResult<(PathBuf, ToolSource)> {
+        // An explicit override never falls back, so a typo doesn't silently pick another binary
+        if let Some(path) = self.overrides.lock().unwrap().get(&tool) {
+            return if path.is_file() {
+                Ok((path.clone(), ToolSource::Override))
+            } else {
+                Err(AppError::MissingTool { tool: tool.name().to_string() })
+            };
+        }
+
+        let binary_name = tool.binary_name();
+
+        // Bundled resources keep their "binaries/<os>/" layout from tauri.conf.json
+        if let Some(resource_dir) = &self.resource_dir {
+            let platform_dir = resource_dir.join("binaries").join(std::env::consts::OS);
+            for dir in [resource_dir, &platform_dir] {
+                let candidate = dir.join(&binary_name);
+                if candidate.is_file() {
+                    return Ok((candidate, ToolSource::Resources));
+                }
+            }
+        }
+
+        if let Some(exe_dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+            let candidate = exe_dir.join(&binary_name);
+            if candidate.is_file() {
+                return Ok((candidate, ToolSource::ExecutableDir));
+            }
+        }
+
+        if let Some(paths) = std::env::var_os("PATH") {
+            for dir in std::env::split_paths(&paths) {
+                let candidate = dir.join(&binary_name);
+                if candidate.is_file() {
+                    return Ok((candidate, ToolSource::Path));
+                }
+            }
+        }
+
+        Err(AppError::MissingTool { tool: tool.name().to_string() })
+    }
+
+    pub fn set_override(&self, tool: Tool, path: Option<PathBuf>) -> AppResult<()> {
+        if let Some(path) = &path {
+            if !path.is_file() {
+                return Err(AppError::InputNotFound { path: path.to_string_lossy().to_string() });
+            }
+        }
+
+        let snapshot = {
+            let mut overrides = self.overrides.lock().unwrap();
+            match path {
+                Some(path) => overrides.insert(tool, path),
+                None => overrides.remove(&tool),
+            };
+            overrides.clone()
+        };
+        self.cache.lock().unwrap().remove(&tool);
+
+        if let Some(config_path) = &self.config_path {
+            if let Some(parent) = config_path.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| AppError::io("Failed to create config directory", e))?;
+            }
+            std::fs::write(config_path, serde_json::to_string_pretty(&snapshot)?)
+                .map_err(|e| AppError::io("Failed to save tool settings", e))?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn status(&self, tool: Tool) -> ToolStatus {
+        let (path, source) = match self.locate(tool) {
+            Ok(found) => found,
+            Err(e) => {
+                return ToolStatus {
+                    tool,
+                    path: None,
+                    source: None,
+                    version: None,
+                    error: Some(e),
+                }
+            }
+        };
+
+        let (version, error) = match tool_version(tool, &path).await {
+            Ok(version) => (Some(version), None),
+            Err(e) => (None, Some(e)),
+        };
+
+        ToolStatus {
+            tool,
+            path: Some(path.to_string_lossy().to_string()),
+            source: Some(source),
+            version,
+            error,
+        }
+    }
+}
+
+// First line of the tool's version output, e.g. "ffmpeg version 7.0.1 Copyright ..." or "2024.08.06"
+async fn tool_version(tool: Tool, path: &Path) -> AppResult<String> {
+    let output = Command::new(path)
+        .args(tool.version_args())
+        .output()
+        .await
+        .map_err(|e| AppError::io(&format!("Failed to execute {}", tool.name()), e))?;
+
+    if !output.status.success() {
+        return Err(AppError::tool_failed(tool.name(), output.status, &String::from_utf8_lossy(&output.stderr)));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().next().unwrap_or_default().trim().to_string())
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+// Karaoke-style vocal reduction. Vocals are usually mixed dead centre, so subtracting one channel
+// from the other removes them, along with anything else in the centre. Bass and kick drum are
+// centred too, so the bands below `low_protect_hz` and above `high_protect_hz` are passed through.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VocalReduction {
+    pub low_protect_hz: f64,
+    pub high_protect_hz: f64,
+}
+
+impl Default for VocalReduction {
+    fn default() -> Self {
+        Self {
+            low_protect_hz: 150.0,
+            high_protect_hz: 8000.0,
+        }
+    }
+}
+
+impl VocalReduction {
+    // Filtergraph segment with one input and one output, so it can sit in a comma-separated chain
+    pub fn filters(&self, channels: u32, sample_rate: u32) -> AppResult<String> {
+        if channels < 2 {
+            return Err(AppError::unsupported("Vocal reduction needs a stereo input, this file is mono"));
+        }
+
+        let nyquist = sample_rate as f64 / 2.0;
+        let (low, high) = (self.low_protect_hz, self.high_protect_hz);
+
+        if !low.is_finite() || !high.is_finite() || low < 20.0 || high <= low || high >= nyquist {
+            return Err(AppError::invalid_option(format!(
+                "Vocal reduction bands must satisfy 20 <= low < high < {} Hz, got {} and {}",
+                nyquist, low, high,
+            )));
+        }
+
+        // Two cascaded 2nd-order filters per edge give a steeper crossover
+        Ok([
+            "aformat=channel_layouts=stereo,asplit=3[vr_low][vr_mid][vr_high]".to_string(),
+            format!("[vr_low]lowpass=f={low},lowpass=f={low}[vr_keep_low]"),
+            format!("[vr_high]highpass=f={high},highpass=f={high}[vr_keep_high]"),
+            format!("[vr_mid]highpass=f={low},highpass=f={low},lowpass=f={high},lowpass=f={high},pan=stereo|c0=c0-c1|c1=c1-c0[vr_side]"),
+            "[vr_keep_low][vr_side][vr_keep_high]amix=inputs=3:normalize=0".to_string(),
+        ]
+        .join(";"))
+    }
+}